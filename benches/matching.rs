@@ -0,0 +1,88 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use launchr::{
+    headless::wait_until_loaded,
+    launcher::PAGE_SIZE,
+    mode::{DmenuMode, MatchKind, Matching, Mode},
+    winit_app::EventHandle,
+};
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+const ITEM_COUNT: usize = 100_000;
+const WORDS: &[&str] = &[
+    "fire", "fox", "thunder", "bird", "code", "visual", "studio", "terminal", "files", "music",
+    "player", "editor", "settings", "browser", "mail", "calendar",
+];
+
+/// Creates a dmenu input with unique lines that look like application and file names
+fn create_input() -> String {
+    let mut input = String::new();
+    for i in 0..ITEM_COUNT {
+        let first = WORDS[i % WORDS.len()];
+        let second = WORDS[(i / WORDS.len()) % WORDS.len()];
+        input.push_str(&format!("{first}-{second}-{i}\n"));
+    }
+    input
+}
+
+/// Measures the latency of the update after each keystroke while typing the query, the matcher
+/// threads finish each keystroke outside of the measurement
+fn bench_query(c: &mut Criterion, name: &str, query: &str, matching: Matching) {
+    let mut mode = DmenuMode::new(None, create_input());
+    let (event_handle, events) = EventHandle::channel();
+    mode.run(event_handle);
+    wait_until_loaded(&mut mode, "", matching, &events);
+
+    c.bench_function(name, |b| {
+        b.iter_custom(|iters| {
+            let mut elapsed = Duration::ZERO;
+            for i in 0..iters as usize {
+                let end = i % query.len() + 1;
+                if end == 1 {
+                    wait_until_loaded(&mut mode, "", matching, &events);
+                }
+                let start = Instant::now();
+                black_box(mode.update(&query[..end], matching, PAGE_SIZE));
+                elapsed += start.elapsed();
+                wait_until_loaded(&mut mode, &query[..end], matching, &events);
+            }
+            elapsed
+        });
+    });
+
+    // the updates that follow while nothing changes, e.g. when the cursor blinks
+    mode.update(query, matching, PAGE_SIZE);
+    wait_until_loaded(&mut mode, query, matching, &events);
+    c.bench_function(&format!("{name}_unchanged"), |b| {
+        b.iter(|| black_box(mode.update(query, matching, PAGE_SIZE)));
+    });
+}
+
+fn bench_keystroke(c: &mut Criterion) {
+    bench_query(c, "matching_keystroke", "firefox", Matching::default());
+}
+
+fn bench_word_boundary(c: &mut Criterion) {
+    let matching = Matching {
+        kind: MatchKind::WordBoundary,
+        ..Matching::default()
+    };
+    bench_query(c, "matching_word_boundary", "fire bird", matching);
+}
+
+fn bench_regex(c: &mut Criterion) {
+    let matching = Matching {
+        kind: MatchKind::Regex,
+        ..Matching::default()
+    };
+    bench_query(c, "matching_regex", "fire.*bird-1", matching);
+}
+
+criterion_group! {
+    name = matching;
+    config = Criterion::default().sample_size(10);
+    targets = bench_keystroke, bench_word_boundary, bench_regex
+}
+criterion_main!(matching);
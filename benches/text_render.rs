@@ -9,8 +9,7 @@ const WIDTH: u32 = 1920;
 const HEIGHT: u32 = 1080;
 
 fn create_ui() -> DynWidget {
-    let mut texts = Vec::new();
-    texts.push(
+    let texts = vec![
         container(
             TextBuilder::new(TEXT)
                 .font(Some("Noto Sans"))
@@ -19,7 +18,7 @@ fn create_ui() -> DynWidget {
         )
         .width(Length::Fill)
         .height(Length::Fill),
-    );
+    ];
     let mut root = container(column(texts))
         .width(Length::Fill)
         .height(Length::Fill)
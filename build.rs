@@ -0,0 +1,180 @@
+//! Generates the table of characters for the emoji mode from the Unicode data files in `data/`,
+//! which are updated with `scripts/update-unicode-data.py`
+
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    fmt::Write as _,
+    fs,
+    path::Path,
+};
+
+const EMOJI_TEST: &str = "data/emoji-test.txt";
+const UNICODE_NAMES: &str = "data/unicode-names.txt";
+const EMOJI_KEYWORDS: &str = "data/emoji-keywords.txt";
+/// In the order of `SkinTone`
+const SKIN_TONES: [&str; 5] = [
+    "light skin tone",
+    "medium-light skin tone",
+    "medium skin tone",
+    "medium-dark skin tone",
+    "dark skin tone",
+];
+
+struct Character {
+    text: String,
+    name: String,
+    group: String,
+    keywords: Vec<String>,
+    skin_tones: [Option<String>; 5],
+}
+
+fn main() {
+    println!("cargo::rerun-if-changed={EMOJI_TEST}");
+    println!("cargo::rerun-if-changed={UNICODE_NAMES}");
+    println!("cargo::rerun-if-changed={EMOJI_KEYWORDS}");
+
+    let mut characters = parse_emoji(&fs::read_to_string(EMOJI_TEST).unwrap());
+    // the annotations leave out the variation selector of emoji like `☺️`
+    let keywords = parse_keywords(&fs::read_to_string(EMOJI_KEYWORDS).unwrap());
+    for character in &mut characters {
+        if let Some(keywords) = keywords.get(&character.text.replace('\u{fe0f}', "")) {
+            character.keywords = keywords.clone();
+        }
+    }
+    let emoji: HashSet<String> = characters
+        .iter()
+        .map(|character| character.text.replace('\u{fe0f}', ""))
+        .collect();
+    for line in fs::read_to_string(UNICODE_NAMES).unwrap().lines() {
+        if line.starts_with('#') || line.is_empty() {
+            continue;
+        }
+        let mut fields = line.split(';');
+        let (Some(code), Some(name), Some(category)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            panic!("invalid line in {UNICODE_NAMES}: {line}");
+        };
+        let text = char::from_u32(u32::from_str_radix(code, 16).unwrap())
+            .unwrap()
+            .to_string();
+        // the emoji have better names, e.g. `red heart` instead of `heavy black heart`
+        if emoji.contains(&text) {
+            continue;
+        }
+        characters.push(Character {
+            text,
+            name: name.to_lowercase(),
+            group: category_name(category).to_string(),
+            keywords: Vec::new(),
+            skin_tones: Default::default(),
+        });
+    }
+
+    let mut out = String::from("static CHARACTERS: &[Character] = &[\n");
+    for character in characters {
+        let skin_tones = if character.skin_tones.iter().all(Option::is_some) {
+            let tones: Vec<String> = character
+                .skin_tones
+                .iter()
+                .map(|tone| format!("{:?}", tone.as_ref().unwrap()))
+                .collect();
+            format!("Some([{}])", tones.join(", "))
+        } else {
+            "None".to_string()
+        };
+        writeln!(
+            out,
+            "    Character {{ text: {:?}, name: {:?}, group: {:?}, keywords: &{:?}, skin_tones: {skin_tones} }},",
+            character.text, character.name, character.group, character.keywords
+        )
+        .unwrap();
+    }
+    out.push_str("];\n");
+    let path = Path::new(&env::var("OUT_DIR").unwrap()).join("characters.rs");
+    fs::write(path, out).unwrap();
+}
+
+/// Parses the fully-qualified emoji, the skin tone variants are added to their emoji
+/// Variants with multiple skin tones, like `handshake: light skin tone, dark skin tone`, are left
+/// out
+fn parse_emoji(text: &str) -> Vec<Character> {
+    let mut characters: Vec<Character> = Vec::new();
+    let mut by_name: HashMap<String, usize> = HashMap::new();
+    let mut group = String::new();
+    for line in text.lines() {
+        if let Some(name) = line.strip_prefix("# group: ") {
+            group = name.to_lowercase();
+            continue;
+        }
+        let Some((codes, rest)) = line.split_once(';') else {
+            continue;
+        };
+        let Some((status, comment)) = rest.split_once('#') else {
+            continue;
+        };
+        if status.trim() != "fully-qualified" {
+            continue;
+        }
+        let text: String = codes
+            .split_whitespace()
+            .map(|code| char::from_u32(u32::from_str_radix(code, 16).unwrap()).unwrap())
+            .collect();
+        // the comment is the emoji, the version it was added in like `E1.0` and the name
+        let name = comment
+            .trim()
+            .splitn(3, ' ')
+            .nth(2)
+            .unwrap_or_else(|| panic!("invalid line in {EMOJI_TEST}: {line}"))
+            .to_string();
+        // the skin tone is the last part of the name, like `waving hand: light skin tone`
+        let last = name.rsplit([':', ',']).next().unwrap().trim();
+        match SKIN_TONES.iter().position(|tone| *tone == last) {
+            Some(i) => {
+                let base = name[..name.len() - last.len()].trim_end_matches([':', ',', ' ']);
+                if let Some(&index) = by_name.get(base) {
+                    characters[index].skin_tones[i] = Some(text);
+                }
+            }
+            None => {
+                by_name.insert(name.clone(), characters.len());
+                characters.push(Character {
+                    text,
+                    name,
+                    group: group.clone(),
+                    keywords: Vec::new(),
+                    skin_tones: Default::default(),
+                });
+            }
+        }
+    }
+    characters
+}
+
+/// Parses the keywords by emoji
+fn parse_keywords(text: &str) -> HashMap<String, Vec<String>> {
+    text.lines()
+        .filter(|line| !line.starts_with('#') && !line.is_empty())
+        .map(|line| {
+            let (text, keywords) = line
+                .split_once('\t')
+                .unwrap_or_else(|| panic!("invalid line in {EMOJI_KEYWORDS}: {line}"));
+            let keywords = keywords.split('|').map(str::to_string).collect();
+            (text.to_string(), keywords)
+        })
+        .collect()
+}
+
+fn category_name(category: &str) -> &'static str {
+    match category {
+        "Sm" => "math symbol",
+        "Sc" => "currency symbol",
+        "Sk" => "modifier symbol",
+        "So" => "symbol",
+        "No" | "Nl" => "number",
+        _ if category.starts_with('P') => "punctuation",
+        _ if category.starts_with('L') => "letter",
+        _ => "other",
+    }
+}
@@ -0,0 +1,55 @@
+#!/usr/bin/env python3
+"""Reference plugin for the launchr plugin protocol, see src/mode/plugin.rs
+
+Offers a few words, the ones that contain the input are streamed back in batches.
+Register it in config.toml:
+
+    [[plugin]]
+    name = "words"
+    command = "python3 /path/to/examples/plugin.py"
+"""
+
+import json
+import sys
+
+PROTOCOL_VERSION = 1
+WORDS = ["apple", "banana", "cherry", "grape", "lemon", "mango", "orange", "pineapple"]
+BATCH_SIZE = 2
+
+
+def send(message):
+    message["jsonrpc"] = "2.0"
+    sys.stdout.write(json.dumps(message) + "\n")
+    sys.stdout.flush()
+
+
+def item(word):
+    return {"text": word, "action": {"type": "shell", "command": f"echo {word}"}}
+
+
+def query(id, input):
+    matches = [item(word) for word in WORDS if input.lower() in word]
+    # stream all but the last batch, the response finishes the query
+    while len(matches) > BATCH_SIZE:
+        batch, matches = matches[:BATCH_SIZE], matches[BATCH_SIZE:]
+        send({"method": "items", "params": {"id": id, "items": batch}})
+    send({"id": id, "result": {"items": matches}})
+
+
+def main():
+    for line in sys.stdin:
+        message = json.loads(line)
+        method = message.get("method")
+        if method == "initialize":
+            send({"id": message["id"], "result": {"protocol_version": PROTOCOL_VERSION}})
+        elif method == "query":
+            query(message["id"], message["params"]["input"])
+        elif method == "cancel":
+            # queries are answered right away, so there is nothing to cancel
+            pass
+        elif "id" in message:
+            send({"id": message["id"], "error": {"code": -32601, "message": "method not found"}})
+
+
+if __name__ == "__main__":
+    main()
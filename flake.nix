@@ -17,8 +17,16 @@
         pkgs = nixpkgs.legacyPackages.${system};
         craneLib = crane.mkLib pkgs;
 
+        # the Unicode data files are needed by build.rs
+        src = pkgs.lib.cleanSourceWith {
+          src = ./.;
+          filter =
+            path: type:
+            (craneLib.filterCargoSources path type) || (builtins.match ".*/data/.*\\.txt$" path != null);
+        };
+
         commonArgs = {
-          src = craneLib.cleanCargoSource ./.;
+          inherit src;
           strictDeps = true;
           buildInputs = with pkgs; [
             fontconfig
@@ -64,6 +72,8 @@
             cargo-machete
             cargo-flamegraph
             gnuplot
+            # runs the reference plugin in the tests
+            python3
           ];
           LD_LIBRARY_PATH = rpath;
         };
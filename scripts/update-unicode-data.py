@@ -0,0 +1,142 @@
+#!/usr/bin/env python3
+"""Updates the Unicode data files in data/ that build.rs generates the emoji mode from
+
+Usage: scripts/update-unicode-data.py <version> [<directory>]
+
+All files are taken from the same Unicode version, like 16.0.0, so the emoji, their keywords and
+the other characters agree. They are downloaded from unicode.org and the CLDR repository, unless a
+directory that contains UnicodeData.txt, emoji-test.txt and the CLDR annotations en.xml of the
+version is given.
+"""
+
+import sys
+import urllib.request
+import xml.etree.ElementTree as ElementTree
+from pathlib import Path
+
+DATA_DIR = Path(__file__).resolve().parent.parent / "data"
+# letters are only kept up to the end of the Cyrillic Supplement block, which covers the Latin,
+# Greek and Cyrillic scripts
+LAST_LETTER = 0x052F
+# the CLDR release that added the annotations of each emoji version
+CLDR_VERSIONS = {"14.0": "40", "15.0": "42", "15.1": "44", "16.0": "46", "17.0": "48"}
+
+
+def read(name, url, directory):
+    if directory:
+        return (Path(directory) / name).read_text(encoding="utf-8")
+    with urllib.request.urlopen(url) as response:
+        return response.read().decode("utf-8")
+
+
+def unicode_names(text, version, url):
+    lines = [
+        "# Code point, name and general category of the symbols, punctuation, numbers and letters of",
+        f"# the Latin, Greek and Cyrillic scripts in UnicodeData.txt {version}, see",
+        f"# {url}",
+        "",
+    ]
+    for line in text.splitlines():
+        code, name, category = line.split(";")[:3]
+        # control characters and ranges like `<CJK Ideograph, First>` have no name
+        if name.startswith("<"):
+            continue
+        is_letter = category.startswith("L") and int(code, 16) <= LAST_LETTER
+        # digits are easier to type than to search
+        if is_letter or (category[0] in "SPN" and category != "Nd"):
+            lines.append(f"{code};{name};{category}")
+    return "\n".join(lines) + "\n"
+
+
+def emoji_test(text, version, url):
+    """Keeps the fully-qualified emoji and their groups, which is all that build.rs reads"""
+    groups = {}
+    group = None
+    for line in text.splitlines():
+        if line.startswith("# Version:") and line.split(":")[1].strip() != version:
+            sys.exit(f"emoji-test.txt is not of version {version}: {line}")
+        if line.startswith("# group: "):
+            group = groups.setdefault(line, [])
+        elif not line.startswith("#") and ";" in line:
+            status = line.split(";")[1].split("#")[0].strip()
+            if status == "fully-qualified":
+                group.append(line)
+    lines = [
+        "# emoji-test.txt",
+        "# Emoji Keyboard/Display Test Data for UTS #51",
+        f"# Version: {version}",
+        "#",
+        f"# Only the fully-qualified emoji of {url},",
+        "# without the subgroups, generated by scripts/update-unicode-data.py.",
+        "#",
+        "# Format:",
+        "#   code points; status # emoji name",
+        "",
+    ]
+    for header, emoji in groups.items():
+        if emoji:
+            lines += [header, ""] + emoji
+    return "\n".join(lines) + "\n"
+
+
+def emoji_keywords(text, cldr_version, url):
+    """Keeps the keywords of the emoji, without the ones that are part of the name anyway"""
+    lines = [
+        f"# Keywords of the emoji in the annotations of CLDR {cldr_version}, see",
+        f"# {url}",
+        "# The keywords that are part of the name of the emoji are left out.",
+        "#",
+        "# Format:",
+        "#   emoji<tab>keyword|keyword",
+        "",
+    ]
+    root = ElementTree.fromstring(text)
+    annotations = root.findall("annotations/annotation")
+    # the names are the annotations of type `tts`, the others are the keywords
+    names = {a.get("cp"): a.text for a in annotations if a.get("type") == "tts"}
+    for annotation in annotations:
+        cp = annotation.get("cp")
+        if annotation.get("type") == "tts" or cp not in names:
+            continue
+        keywords = [
+            keyword.strip()
+            for keyword in annotation.text.split("|")
+            if keyword.strip().lower() not in names[cp].lower()
+        ]
+        if keywords:
+            lines.append(f"{cp}\t{'|'.join(keywords)}")
+    return "\n".join(lines) + "\n"
+
+
+def main():
+    if len(sys.argv) not in (2, 3):
+        sys.exit(__doc__)
+    version = sys.argv[1]
+    directory = sys.argv[2] if len(sys.argv) == 3 else None
+    # the emoji versions only have a major and a minor version
+    emoji_version = ".".join(version.split(".")[:2])
+
+    names_url = f"https://www.unicode.org/Public/{version}/ucd/UnicodeData.txt"
+    names = read("UnicodeData.txt", names_url, directory)
+    emoji_url = f"https://www.unicode.org/Public/emoji/{emoji_version}/emoji-test.txt"
+    emoji = read("emoji-test.txt", emoji_url, directory)
+    cldr_version = CLDR_VERSIONS.get(emoji_version)
+    if cldr_version is None:
+        sys.exit(f"unknown CLDR version of emoji {emoji_version}, add it to CLDR_VERSIONS")
+    keywords_url = (
+        "https://raw.githubusercontent.com/unicode-org/cldr/"
+        f"release-{cldr_version}/common/annotations/en.xml"
+    )
+    keywords = read("en.xml", keywords_url, directory)
+
+    # all files are converted before writing any, so a failure does not mix versions
+    names = unicode_names(names, version, names_url)
+    emoji = emoji_test(emoji, emoji_version, emoji_url)
+    keywords = emoji_keywords(keywords, cldr_version, keywords_url)
+    (DATA_DIR / "unicode-names.txt").write_text(names, encoding="utf-8")
+    (DATA_DIR / "emoji-test.txt").write_text(emoji, encoding="utf-8")
+    (DATA_DIR / "emoji-keywords.txt").write_text(keywords, encoding="utf-8")
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,700 @@
+//! Evaluates calculator input like `2^10 * 3`, `sqrt(2) / 2`, `0xff + 1`, `255 in hex` or
+//! `12 km in miles`
+
+use anyhow::{Result, anyhow, bail};
+use std::{
+    f64::consts::{E, PI, TAU},
+    fmt::{self, Display, Formatter},
+};
+
+/// Name of the source that is shown next to a result of the calculator inside another mode
+pub const INLINE_SOURCE: &str = "calc";
+
+/// The result of a calculation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Answer {
+    pub text: String,
+    /// Whether the input was a single number or constant without any operation, e.g. `42`, which
+    /// is not worth showing as a result inside other modes
+    pub trivial: bool,
+}
+
+/// Evaluates the input, fails if it is not a valid expression
+pub fn evaluate(input: &str) -> Result<Answer> {
+    let tokens = tokenize(input)?;
+    if tokens.is_empty() {
+        bail!("empty expression");
+    }
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        trivial: true,
+    };
+    let value = parser.expression()?;
+    if !value.value.is_finite() {
+        bail!("the result is not a finite number");
+    }
+    let text = match parser.next() {
+        None => value.to_string(),
+        Some(Token::Ident(word)) if matches!(word.as_str(), "in" | "to" | "as") => {
+            parser.trivial = false;
+            let target = match parser.next() {
+                Some(Token::Ident(target)) => target,
+                _ => bail!("expected a unit or base after '{word}'"),
+            };
+            if parser.position < parser.tokens.len() {
+                bail!("unexpected input after '{target}'");
+            }
+            value.convert(&target)?
+        }
+        Some(token) => bail!("unexpected {token}"),
+    };
+    Ok(Answer {
+        text,
+        trivial: parser.trivial,
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Operator(char),
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Token::Number(number) => write!(f, "number {number}"),
+            Token::Ident(ident) => write!(f, "'{ident}'"),
+            Token::Operator(operator) => write!(f, "'{operator}'"),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                // the sign of an exponent like `1e-3`
+                let exponent_sign = (c == '-' || c == '+')
+                    && input[start..i].ends_with(['e', 'E'])
+                    && !input[start..].starts_with("0x");
+                if !(c.is_ascii_alphanumeric() || c == '.' || c == '_' || exponent_sign) {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            let (number, unit) = split_number(&input[start..end]);
+            tokens.push(Token::Number(parse_number(number)?));
+            // a unit without a space, like `12km`
+            if !unit.is_empty() {
+                tokens.push(Token::Ident(unit.to_lowercase()));
+            }
+        } else if c.is_alphabetic() || c == '°' || c == '_' {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                if !(c.is_alphanumeric() || c == '°' || c == '_') {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            tokens.push(Token::Ident(input[start..end].to_lowercase()));
+        } else {
+            let operator = match c {
+                '×' => '*',
+                '÷' => '/',
+                '−' => '-',
+                '+' | '-' | '*' | '/' | '%' | '^' | '(' | ')' | ',' => c,
+                _ => bail!("unexpected character '{c}'"),
+            };
+            tokens.push(Token::Operator(operator));
+            chars.next();
+        }
+    }
+    Ok(tokens)
+}
+
+/// Splits a word that starts with a digit into the number and a unit, e.g. `12km`
+fn split_number(word: &str) -> (&str, &str) {
+    if word.starts_with("0x") || word.starts_with("0b") || word.starts_with("0o") {
+        return (word, "");
+    }
+    let mut end = word.len();
+    for (i, c) in word.char_indices() {
+        let is_exponent = (c == 'e' || c == 'E')
+            && word[i + 1..]
+                .trim_start_matches(['-', '+'])
+                .starts_with(|c: char| c.is_ascii_digit());
+        if c.is_alphabetic() && !is_exponent {
+            end = i;
+            break;
+        }
+    }
+    word.split_at(end)
+}
+
+fn parse_number(text: &str) -> Result<f64> {
+    let digits = text.replace('_', "");
+    let radix = match digits.get(..2) {
+        Some("0x") => 16,
+        Some("0b") => 2,
+        Some("0o") => 8,
+        _ => {
+            return digits
+                .parse()
+                .map_err(|_| anyhow!("invalid number '{text}'"));
+        }
+    };
+    i64::from_str_radix(&digits[2..], radix)
+        .map(|number| number as f64)
+        .map_err(|_| anyhow!("invalid number '{text}'"))
+}
+
+/// A number, optionally with a unit
+#[derive(Debug, Clone, Copy)]
+struct Quantity {
+    value: f64,
+    unit: Option<&'static Unit>,
+}
+
+impl Quantity {
+    fn number(value: f64) -> Self {
+        Self { value, unit: None }
+    }
+
+    /// Converts to the unit of `other`, so both can be added or subtracted
+    fn to_unit_of(self, other: Quantity) -> Result<f64> {
+        match (self.unit, other.unit) {
+            (Some(from), Some(to)) => from.convert(self.value, to),
+            _ => Ok(self.value),
+        }
+    }
+
+    fn convert(self, target: &str) -> Result<String> {
+        let base = match target {
+            "hex" | "hexadecimal" => Some(16),
+            "bin" | "binary" => Some(2),
+            "oct" | "octal" => Some(8),
+            "dec" | "decimal" => Some(10),
+            _ => None,
+        };
+        if let Some(base) = base {
+            if self.value.fract() != 0.0 || self.value.abs() >= i64::MAX as f64 {
+                bail!("only integers can be shown in another base");
+            }
+            let value = self.value as i64;
+            let sign = if value < 0 { "-" } else { "" };
+            let magnitude = value.unsigned_abs();
+            return Ok(match base {
+                16 => format!("{sign}{magnitude:#x}"),
+                2 => format!("{sign}{magnitude:#b}"),
+                8 => format!("{sign}{magnitude:#o}"),
+                _ => value.to_string(),
+            });
+        }
+        let to = Unit::find(target).ok_or_else(|| anyhow!("unknown unit '{target}'"))?;
+        let from = self
+            .unit
+            .ok_or_else(|| anyhow!("a number without a unit can not be converted"))?;
+        let quantity = Quantity {
+            value: from.convert(self.value, to)?,
+            unit: Some(to),
+        };
+        if !quantity.value.is_finite() {
+            bail!("the converted value is not a finite number");
+        }
+        Ok(quantity.to_string())
+    }
+}
+
+impl Display for Quantity {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", format_number(self.value))?;
+        if let Some(unit) = self.unit {
+            write!(f, " {}", unit.symbol)?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats the number without the rounding errors of floating point numbers, e.g. `0.3` instead
+/// of `0.30000000000000004`
+fn format_number(value: f64) -> String {
+    if value == 0.0 {
+        // no negative zero
+        return "0".to_string();
+    }
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        return format!("{}", value as i64);
+    }
+    if value.abs() < 1e-9 || value.abs() >= 1e15 {
+        return format!("{value:e}");
+    }
+    let text = format!("{value:.10}");
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dimension {
+    Length,
+    Mass,
+    Time,
+    Temperature,
+    Volume,
+    Data,
+}
+
+/// A unit that converts to the base unit of its dimension with `(value + offset) * factor`
+#[derive(Debug, PartialEq)]
+struct Unit {
+    names: &'static [&'static str],
+    symbol: &'static str,
+    dimension: Dimension,
+    factor: f64,
+    offset: f64,
+}
+
+const fn unit(
+    names: &'static [&'static str],
+    symbol: &'static str,
+    dimension: Dimension,
+    factor: f64,
+) -> Unit {
+    Unit {
+        names,
+        symbol,
+        dimension,
+        factor,
+        offset: 0.0,
+    }
+}
+
+/// Names are lowercase, `in` is left out because it is used for conversions
+const UNITS: &[Unit] = &[
+    unit(
+        &["mm", "millimeter", "millimeters"],
+        "mm",
+        Dimension::Length,
+        0.001,
+    ),
+    unit(
+        &["cm", "centimeter", "centimeters"],
+        "cm",
+        Dimension::Length,
+        0.01,
+    ),
+    unit(
+        &["m", "meter", "meters", "metre", "metres"],
+        "m",
+        Dimension::Length,
+        1.0,
+    ),
+    unit(
+        &["km", "kilometer", "kilometers"],
+        "km",
+        Dimension::Length,
+        1000.0,
+    ),
+    unit(&["inch", "inches"], "in", Dimension::Length, 0.0254),
+    unit(&["ft", "foot", "feet"], "ft", Dimension::Length, 0.3048),
+    unit(&["yd", "yard", "yards"], "yd", Dimension::Length, 0.9144),
+    unit(&["mi", "mile", "miles"], "mi", Dimension::Length, 1609.344),
+    unit(
+        &["mg", "milligram", "milligrams"],
+        "mg",
+        Dimension::Mass,
+        0.001,
+    ),
+    unit(&["g", "gram", "grams"], "g", Dimension::Mass, 1.0),
+    unit(
+        &["kg", "kilogram", "kilograms"],
+        "kg",
+        Dimension::Mass,
+        1000.0,
+    ),
+    unit(&["t", "tonne", "tonnes"], "t", Dimension::Mass, 1e6),
+    unit(
+        &["oz", "ounce", "ounces"],
+        "oz",
+        Dimension::Mass,
+        28.349523125,
+    ),
+    unit(
+        &["lb", "lbs", "pound", "pounds"],
+        "lb",
+        Dimension::Mass,
+        453.59237,
+    ),
+    unit(
+        &["ms", "millisecond", "milliseconds"],
+        "ms",
+        Dimension::Time,
+        0.001,
+    ),
+    unit(
+        &["s", "sec", "second", "seconds"],
+        "s",
+        Dimension::Time,
+        1.0,
+    ),
+    unit(&["min", "minute", "minutes"], "min", Dimension::Time, 60.0),
+    unit(&["h", "hr", "hour", "hours"], "h", Dimension::Time, 3600.0),
+    unit(&["d", "day", "days"], "d", Dimension::Time, 86400.0),
+    unit(&["wk", "week", "weeks"], "wk", Dimension::Time, 604800.0),
+    unit(&["yr", "year", "years"], "yr", Dimension::Time, 31557600.0),
+    unit(&["k", "kelvin"], "K", Dimension::Temperature, 1.0),
+    Unit {
+        names: &["c", "°c", "celsius"],
+        symbol: "°C",
+        dimension: Dimension::Temperature,
+        factor: 1.0,
+        offset: 273.15,
+    },
+    Unit {
+        names: &["f", "°f", "fahrenheit"],
+        symbol: "°F",
+        dimension: Dimension::Temperature,
+        factor: 5.0 / 9.0,
+        offset: 459.67,
+    },
+    unit(
+        &["ml", "milliliter", "milliliters"],
+        "ml",
+        Dimension::Volume,
+        0.001,
+    ),
+    unit(
+        &["l", "liter", "liters", "litre", "litres"],
+        "l",
+        Dimension::Volume,
+        1.0,
+    ),
+    unit(
+        &["gal", "gallon", "gallons"],
+        "gal",
+        Dimension::Volume,
+        3.785411784,
+    ),
+    unit(&["bit", "bits"], "bit", Dimension::Data, 0.125),
+    unit(&["b", "byte", "bytes"], "B", Dimension::Data, 1.0),
+    unit(&["kb", "kilobyte", "kilobytes"], "kB", Dimension::Data, 1e3),
+    unit(&["mb", "megabyte", "megabytes"], "MB", Dimension::Data, 1e6),
+    unit(&["gb", "gigabyte", "gigabytes"], "GB", Dimension::Data, 1e9),
+    unit(
+        &["tb", "terabyte", "terabytes"],
+        "TB",
+        Dimension::Data,
+        1e12,
+    ),
+    unit(
+        &["kib", "kibibyte", "kibibytes"],
+        "KiB",
+        Dimension::Data,
+        1024.0,
+    ),
+    unit(
+        &["mib", "mebibyte", "mebibytes"],
+        "MiB",
+        Dimension::Data,
+        1048576.0,
+    ),
+    unit(
+        &["gib", "gibibyte", "gibibytes"],
+        "GiB",
+        Dimension::Data,
+        1073741824.0,
+    ),
+    unit(
+        &["tib", "tebibyte", "tebibytes"],
+        "TiB",
+        Dimension::Data,
+        1099511627776.0,
+    ),
+];
+
+impl Unit {
+    fn find(name: &str) -> Option<&'static Unit> {
+        UNITS.iter().find(|unit| unit.names.contains(&name))
+    }
+
+    fn convert(&self, value: f64, to: &Unit) -> Result<f64> {
+        if self.dimension != to.dimension {
+            bail!("can not convert {} to {}", self.symbol, to.symbol);
+        }
+        let base = (value + self.offset) * self.factor;
+        Ok(base / to.factor - to.offset)
+    }
+}
+
+fn constant(name: &str) -> Option<f64> {
+    match name {
+        "pi" | "π" => Some(PI),
+        "tau" | "τ" => Some(TAU),
+        "e" => Some(E),
+        _ => None,
+    }
+}
+
+fn function(name: &str, args: &[f64]) -> Result<f64> {
+    let value = match (name, args) {
+        ("sqrt", [x]) => x.sqrt(),
+        ("cbrt", [x]) => x.cbrt(),
+        ("abs", [x]) => x.abs(),
+        ("floor", [x]) => x.floor(),
+        ("ceil", [x]) => x.ceil(),
+        ("round", [x]) => x.round(),
+        ("trunc", [x]) => x.trunc(),
+        ("exp", [x]) => x.exp(),
+        ("ln", [x]) => x.ln(),
+        ("log", [x]) => x.log10(),
+        ("log", [x, base]) => x.log(*base),
+        ("log2", [x]) => x.log2(),
+        ("sin", [x]) => x.sin(),
+        ("cos", [x]) => x.cos(),
+        ("tan", [x]) => x.tan(),
+        ("asin", [x]) => x.asin(),
+        ("acos", [x]) => x.acos(),
+        ("atan", [x]) => x.atan(),
+        ("atan2", [y, x]) => y.atan2(*x),
+        ("min", [x, y]) => x.min(*y),
+        ("max", [x, y]) => x.max(*y),
+        ("pow", [x, y]) => x.powf(*y),
+        _ => bail!("unknown function '{name}' with {} arguments", args.len()),
+    };
+    Ok(value)
+}
+
+/// Recursive descent parser that evaluates while parsing, from the lowest precedence:
+/// `+ -`, `* / %`, unary `-`, `^` (right associative), numbers with a unit, and functions,
+/// constants and parentheses
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    /// No operation was performed yet
+    trivial: bool,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn eat(&mut self, operator: char) -> bool {
+        if self.peek() == Some(&Token::Operator(operator)) {
+            self.position += 1;
+            self.trivial = false;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, operator: char) -> Result<()> {
+        match self.next() {
+            Some(Token::Operator(c)) if c == operator => Ok(()),
+            Some(token) => bail!("expected '{operator}' but found {token}"),
+            None => bail!("expected '{operator}'"),
+        }
+    }
+
+    fn expression(&mut self) -> Result<Quantity> {
+        let mut left = self.term()?;
+        loop {
+            let sign = if self.eat('+') {
+                1.0
+            } else if self.eat('-') {
+                -1.0
+            } else {
+                return Ok(left);
+            };
+            let right = self.term()?;
+            if left.unit.is_none() != right.unit.is_none() {
+                bail!("can not add a number with and a number without a unit");
+            }
+            left.value += sign * right.to_unit_of(left)?;
+        }
+    }
+
+    fn term(&mut self) -> Result<Quantity> {
+        let mut left = self.unary()?;
+        loop {
+            let operator = ['*', '/', '%']
+                .into_iter()
+                .find(|&operator| self.eat(operator));
+            let Some(operator) = operator else {
+                return Ok(left);
+            };
+            let right = self.unary()?;
+            let unit = match (left.unit, right.unit) {
+                (Some(_), Some(_)) => bail!("units can only be multiplied with numbers"),
+                (None, Some(_)) if operator != '*' => bail!("can not divide by a unit"),
+                (unit, None) | (None, unit) => unit,
+            };
+            let value = match operator {
+                '*' => left.value * right.value,
+                '/' if right.value == 0.0 => bail!("division by zero"),
+                '/' => left.value / right.value,
+                _ if right.value == 0.0 => bail!("division by zero"),
+                _ => left.value % right.value,
+            };
+            left = Quantity { value, unit };
+        }
+    }
+
+    fn unary(&mut self) -> Result<Quantity> {
+        if self.eat('-') {
+            let quantity = self.unary()?;
+            return Ok(Quantity {
+                value: -quantity.value,
+                ..quantity
+            });
+        }
+        if self.eat('+') {
+            return self.unary();
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> Result<Quantity> {
+        let base = self.quantity()?;
+        if !self.eat('^') {
+            return Ok(base);
+        }
+        let exponent = self.unary()?;
+        if base.unit.is_some() || exponent.unit.is_some() {
+            bail!("units can not be raised to a power");
+        }
+        Ok(Quantity::number(base.value.powf(exponent.value)))
+    }
+
+    /// A number or parenthesized expression, optionally followed by a unit
+    fn quantity(&mut self) -> Result<Quantity> {
+        let mut quantity = self.primary()?;
+        if let Some(Token::Ident(name)) = self.peek()
+            && let Some(unit) = Unit::find(name)
+        {
+            if quantity.unit.is_some() {
+                bail!("a quantity can only have one unit");
+            }
+            quantity.unit = Some(unit);
+            self.position += 1;
+        }
+        Ok(quantity)
+    }
+
+    fn primary(&mut self) -> Result<Quantity> {
+        match self.next() {
+            Some(Token::Number(number)) => Ok(Quantity::number(number)),
+            Some(Token::Operator('(')) => {
+                let quantity = self.expression()?;
+                self.expect(')')?;
+                Ok(quantity)
+            }
+            Some(Token::Ident(name)) => {
+                if self.peek() == Some(&Token::Operator('(')) {
+                    self.position += 1;
+                    self.trivial = false;
+                    let mut args = vec![self.number()?];
+                    while self.eat(',') {
+                        args.push(self.number()?);
+                    }
+                    self.expect(')')?;
+                    return Ok(Quantity::number(function(&name, &args)?));
+                }
+                constant(&name)
+                    .map(Quantity::number)
+                    .ok_or_else(|| anyhow!("unknown name '{name}'"))
+            }
+            Some(token) => bail!("unexpected {token}"),
+            None => bail!("unexpected end of the expression"),
+        }
+    }
+
+    /// An argument of a function, which can not have a unit
+    fn number(&mut self) -> Result<f64> {
+        let quantity = self.expression()?;
+        if quantity.unit.is_some() {
+            bail!("functions only take numbers without a unit");
+        }
+        Ok(quantity.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(input: &str) -> String {
+        evaluate(input).unwrap().text
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(eval("2^10 * 3"), "3072");
+        assert_eq!(eval("1 + 2 * 3"), "7");
+        assert_eq!(eval("(1 + 2) * 3"), "9");
+        assert_eq!(eval("-2^2"), "-4");
+        assert_eq!(eval("2^3^2"), "512");
+        assert_eq!(eval("0.1 + 0.2"), "0.3");
+        assert_eq!(eval("7 % 4"), "3");
+        assert_eq!(eval("1.5e3 / 2"), "750");
+        assert!(evaluate("1 / 0").is_err());
+        assert!(evaluate("10^400").is_err());
+        assert!(evaluate("-10^400").is_err());
+        assert!(evaluate("sqrt(-1)").is_err());
+        assert!(evaluate("1e308 km in mm").is_err());
+    }
+
+    #[test]
+    fn test_functions_and_constants() {
+        assert_eq!(eval("sqrt(16) + abs(-2)"), "6");
+        assert_eq!(eval("max(2, 3 * 2)"), "6");
+        assert_eq!(eval("round(pi * 100) / 100"), "3.14");
+        assert_eq!(eval("log(1000)"), "3");
+        assert!(evaluate("foo(1)").is_err());
+    }
+
+    #[test]
+    fn test_bases() {
+        assert_eq!(eval("0xff + 0b1"), "256");
+        assert_eq!(eval("255 in hex"), "0xff");
+        assert_eq!(eval("10 to bin"), "0b1010");
+        assert_eq!(eval("-0o17 in oct"), "-0o17");
+        assert!(evaluate("1.5 in hex").is_err());
+    }
+
+    #[test]
+    fn test_units() {
+        assert_eq!(eval("12 km in miles"), "7.4564543068 mi");
+        assert_eq!(eval("100c to f"), "212 °F");
+        assert_eq!(eval("1 mi + 1 km in m"), "2609.344 m");
+        assert_eq!(eval("2 * 3 ft in inch"), "72 in");
+        assert_eq!(eval("1 GiB in mb"), "1073.741824 MB");
+        assert!(evaluate("1 kg in m").is_err());
+        assert!(evaluate("1 km + 1").is_err());
+    }
+
+    #[test]
+    fn test_trivial() {
+        assert!(evaluate("42").unwrap().trivial);
+        assert!(evaluate("pi").unwrap().trivial);
+        assert!(!evaluate("1 + 1").unwrap().trivial);
+        assert!(!evaluate("12 km in m").unwrap().trivial);
+        assert!(evaluate("firefox").is_err());
+        assert!(evaluate("").is_err());
+    }
+}
@@ -0,0 +1,124 @@
+use anyhow::{Context, Result};
+use arboard::SetExtLinux;
+use std::{
+    cell::RefCell,
+    env,
+    io::{Read, Write, stdin},
+    os::unix::process::CommandExt,
+    process::{Command, Stdio},
+    rc::Rc,
+    thread,
+};
+
+/// Flag that makes launchr serve the clipboard text read from stdin, see [`serve_text`]
+pub const SERVE_FLAG: &str = "serve-clipboard";
+
+/// Access to a clipboard, so the system clipboard can be replaced in tests
+pub trait Clipboard {
+    fn get_text(&mut self) -> Result<String>;
+    /// Sets the text, which stays available after the launcher exits
+    fn set_text(&mut self, text: &str) -> Result<()>;
+}
+
+/// The clipboard of the desktop, uses the data-control protocol on Wayland and the clipboard
+/// selection on X11
+pub struct SystemClipboard(arboard::Clipboard);
+
+impl SystemClipboard {
+    pub fn new() -> Result<Self> {
+        Ok(Self(arboard::Clipboard::new()?))
+    }
+}
+
+impl Clipboard for SystemClipboard {
+    fn get_text(&mut self) -> Result<String> {
+        Ok(self.0.get_text()?)
+    }
+
+    /// The text is only served while the process that set it is running, so it is handed to a
+    /// helper process that keeps serving it until something else is copied
+    fn set_text(&mut self, text: &str) -> Result<()> {
+        let mut child = Command::new(env::current_exe()?)
+            .arg(format!("--{SERVE_FLAG}"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            // signals sent to the group of the launcher must not stop the helper
+            .process_group(0)
+            .spawn()
+            .context("failed to start the clipboard helper")?;
+        let res = child.stdin.take().unwrap().write_all(text.as_bytes());
+        // reap the helper if it exits while the launcher is still running
+        thread::spawn(move || child.wait());
+        Ok(res?)
+    }
+}
+
+/// Sets the clipboard to the text read from stdin and serves it until it is replaced, run by the
+/// helper process started by [`SystemClipboard`]
+pub fn serve_text() -> Result<()> {
+    let mut text = String::new();
+    stdin().read_to_string(&mut text)?;
+    arboard::Clipboard::new()?.set().wait().text(text)?;
+    Ok(())
+}
+
+/// A clipboard that only lives in memory, clones share the same contents
+#[doc(hidden)]
+#[derive(Debug, Clone, Default)]
+pub struct FakeClipboard {
+    text: Rc<RefCell<String>>,
+}
+
+impl FakeClipboard {
+    pub fn text(&self) -> String {
+        self.text.borrow().clone()
+    }
+}
+
+impl Clipboard for FakeClipboard {
+    fn get_text(&mut self) -> Result<String> {
+        Ok(self.text())
+    }
+
+    fn set_text(&mut self, text: &str) -> Result<()> {
+        *self.text.borrow_mut() = text.to_string();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::{Action, Item};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_copy_items() {
+        let mut clipboard = FakeClipboard::default();
+        let file = Item::new(
+            "notes.md".to_string(),
+            Action::File {
+                path: PathBuf::from("/home/user/notes.md"),
+                is_dir: false,
+            },
+        );
+        clipboard.set_text(&file.copy_text()).unwrap();
+        assert_eq!(clipboard.clone().get_text().unwrap(), "/home/user/notes.md");
+        let program = Item::new(
+            "Firefox".to_string(),
+            Action::Exec {
+                program: "firefox".to_string(),
+                args: vec![
+                    "--private-window".to_string(),
+                    "https://example.com/?q=it's here".to_string(),
+                ],
+                terminal: false,
+            },
+        );
+        clipboard.set_text(&program.copy_text()).unwrap();
+        assert_eq!(
+            clipboard.text(),
+            r"firefox --private-window 'https://example.com/?q=it'\''s here'"
+        );
+    }
+}
@@ -1,4 +1,8 @@
-use crate::ui::Color;
+use crate::{
+    keybindings::Keybindings,
+    mode::{CustomModeConfig, EmojiConfig, Matching, PluginConfig},
+    ui::Color,
+};
 use anyhow::Result;
 use serde::Deserialize;
 use std::fs;
@@ -10,6 +14,15 @@ const CONFIG_DIR_NAME: &str = env!("CARGO_CRATE_NAME");
 pub struct Config {
     pub font: FontConfig,
     pub color: ColorConfig,
+    pub matching: Matching,
+    pub keybindings: Keybindings,
+    pub emoji: EmojiConfig,
+    /// The `[[mode]]` tables, modes that are defined by the user
+    #[serde(rename = "mode")]
+    pub modes: Vec<CustomModeConfig>,
+    /// The `[[plugin]]` tables, modes that run in a separate process
+    #[serde(rename = "plugin")]
+    pub plugins: Vec<PluginConfig>,
 }
 
 impl Config {
@@ -55,6 +68,8 @@ pub struct ColorConfig {
     pub foreground_second: Color,
     pub primary: Color,
     pub secondary: Color,
+    /// Used to highlight the matched characters
+    pub accent: Color,
 }
 
 impl Default for ColorConfig {
@@ -66,6 +81,7 @@ impl Default for ColorConfig {
             foreground_second: Color::from_rgba(150, 150, 150, 255),
             primary: Color::from_rgba(50, 50, 200, 255),
             secondary: Color::from_rgba(50, 200, 50, 255),
+            accent: Color::from_rgba(250, 180, 60, 255),
         }
     }
 }
@@ -0,0 +1,60 @@
+use crate::{
+    config::Config,
+    launcher,
+    mode::{Matching, Mode},
+    recent::RecentItems,
+    winit_app::{EventHandle, UserEvent},
+};
+use std::{
+    io::{self, Write},
+    sync::mpsc,
+    time::Duration,
+};
+
+/// Runs a single query against the mode without opening a window and prints the ranked matches
+/// to stdout, either as plain lines or as a JSON array
+pub fn print_matches(
+    mut mode: Box<dyn Mode>,
+    config: &Config,
+    query: &str,
+    json: bool,
+) -> anyhow::Result<()> {
+    let recent = RecentItems::load_or_default()?;
+    let matching = launcher::mode_matching(mode.as_ref(), config.matching);
+    let (event_handle, events) = EventHandle::channel();
+    mode.run(event_handle);
+    wait_until_loaded(mode.as_mut(), query, matching, &events);
+    let matches =
+        launcher::find_matches(mode.as_mut(), &recent, query, matching, launcher::PAGE_SIZE);
+
+    let mut stdout = io::stdout().lock();
+    if json {
+        serde_json::to_writer_pretty(&mut stdout, &matches)?;
+        writeln!(stdout)?;
+    } else {
+        for r#match in matches {
+            writeln!(stdout, "{}", r#match.item.text)?;
+        }
+    }
+    Ok(())
+}
+
+/// Updates the mode until it has loaded and matched all its items
+/// The events are those of the handle that was passed to [`Mode::run`]
+pub fn wait_until_loaded(
+    mode: &mut dyn Mode,
+    query: &str,
+    matching: Matching,
+    events: &mpsc::Receiver<UserEvent>,
+) {
+    // the mode is updated while loading so it can process items incrementally, only the progress
+    // is needed, so no matches are requested
+    loop {
+        mode.update(query, matching, 0);
+        // the status is read after the update, so it tells whether that update matched all items
+        if !mode.status().loading {
+            break;
+        }
+        let _ = events.recv_timeout(Duration::from_millis(50));
+    }
+}
@@ -2,7 +2,7 @@ use serde::{Deserialize, Serialize};
 use std::{
     fmt::{self, Display, Formatter},
     os::unix::process::CommandExt,
-    path::PathBuf,
+    path::{Path, PathBuf},
     process::{Command, Stdio},
 };
 
@@ -23,6 +23,33 @@ fn spawn_detached(program: &str, args: &[String]) -> std::io::Result<()> {
     command.spawn().map(|_| ())
 }
 
+/// Checks if a program is a path to an existing file or can be found in $PATH
+fn program_exists(program: &str) -> bool {
+    if program.contains('/') {
+        return Path::new(program).exists();
+    }
+    std::env::var_os("PATH")
+        .is_some_and(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).exists()))
+}
+
+/// Quotes the text so the shell passes it as a single argument
+pub(crate) fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+/// Quotes the argument unless the shell would pass it unchanged anyway, like `--private-window`
+fn quote_if_needed(arg: &str) -> String {
+    let is_plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_alphanumeric() || "-_./=:,+@%".contains(c));
+    if is_plain {
+        arg.to_string()
+    } else {
+        shell_quote(arg)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct Item {
     pub text: String,
@@ -41,6 +68,15 @@ pub enum Action {
         path: PathBuf,
         is_dir: bool,
     },
+    /// A command line that is run by the shell
+    Shell {
+        command: String,
+    },
+    /// Text that is copied to the clipboard, like the result of a calculation or a character
+    /// whose name is shown as the text of the item
+    Copy {
+        text: String,
+    },
 }
 
 impl AsRef<str> for Item {
@@ -52,16 +88,10 @@ impl AsRef<str> for Item {
 impl Display for Item {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match &self.action {
-            Action::Selection => write!(f, "{}", self.text),
-            Action::Exec { program, args, .. } => write!(
-                f,
-                "{} ({})",
-                self.text,
-                std::iter::once(program.as_str())
-                    .chain(args.iter().map(String::as_str))
-                    .collect::<Vec<_>>()
-                    .join(" "),
-            ),
+            Action::Selection | Action::Shell { .. } | Action::Copy { .. } => {
+                write!(f, "{}", self.text)
+            }
+            Action::Exec { .. } => write!(f, "{} ({})", self.text, self.copy_text()),
             Action::File { is_dir, .. } => {
                 write!(f, "{} {}", if *is_dir { 'D' } else { 'F' }, self.text)
             }
@@ -78,6 +108,39 @@ impl Item {
         Self::new(text, Action::Selection)
     }
 
+    /// Byte offset of the text in the displayed string, see the [`Display`] implementation
+    pub fn display_text_offset(&self) -> usize {
+        match self.action {
+            Action::File { .. } => 2,
+            _ => 0,
+        }
+    }
+
+    /// The text that is copied to the clipboard: the path of a file, the command line of a
+    /// program, the text of a selection or the text to copy
+    pub fn copy_text(&self) -> String {
+        match &self.action {
+            Action::Selection => self.text.clone(),
+            Action::Copy { text } => text.clone(),
+            Action::Exec { program, args, .. } => std::iter::once(program)
+                .chain(args)
+                .map(|arg| quote_if_needed(arg))
+                .collect::<Vec<_>>()
+                .join(" "),
+            Action::File { path, .. } => path.display().to_string(),
+            Action::Shell { command } => command.clone(),
+        }
+    }
+
+    /// Whether the file or program of the item still exists
+    pub fn is_available(&self) -> bool {
+        match &self.action {
+            Action::Selection | Action::Shell { .. } | Action::Copy { .. } => true,
+            Action::Exec { program, .. } => program_exists(program),
+            Action::File { path, .. } => path.exists(),
+        }
+    }
+
     pub fn exec(&self) {
         match &self.action {
             Action::Exec {
@@ -104,14 +167,22 @@ impl Item {
                     }
                 }
             }
+            Action::Shell { command } => {
+                log::info!("running shell command: {command}");
+                if let Err(e) = spawn_detached("sh", &["-c".to_string(), command.clone()]) {
+                    eprintln!("Failed to run shell command: {e}");
+                }
+            }
             Action::Selection => {
                 // Print the selected item
                 println!("{}", self.text);
             }
+            // the launcher copies the text, it is printed when there is no clipboard
+            Action::Copy { text } => println!("{text}"),
             Action::File { path, .. } => {
                 // Open the file using default software
                 log::info!("opening: '{}'", path.display());
-                if let Err(e) = open::that_detached(&path) {
+                if let Err(e) = open::that_detached(path) {
                     eprintln!("Failed to open '{}': {}", path.display(), e);
                 }
             }
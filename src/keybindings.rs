@@ -0,0 +1,311 @@
+use anyhow::{Context, anyhow, bail};
+use serde::Deserialize;
+use std::{collections::HashMap, str::FromStr};
+use winit::keyboard::{KeyCode, ModifiersState};
+
+/// An action of the launcher that can be bound to a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyAction {
+    SelectNext,
+    SelectPrevious,
+    PageDown,
+    PageUp,
+    SelectFirst,
+    SelectLast,
+    /// Executes the selected or marked matches
+    Accept,
+    /// Executes the input itself instead of a match, e.g. a command that is not in `$PATH`
+    AcceptCustom,
+    /// Marks the selected match, so multiple matches can be accepted at once
+    ToggleMark,
+    Close,
+    /// Removes the selected match from the history
+    Forget,
+    TogglePin,
+    CycleMatching,
+    /// Deletes the word before the cursor
+    DeleteWord,
+    /// Deletes the input before the cursor
+    KillLine,
+    /// Inserts the text that was last deleted with `delete-word` or `kill-line`
+    Yank,
+    LineStart,
+    LineEnd,
+    WordLeft,
+    WordRight,
+    Undo,
+    Redo,
+    /// Inserts the text of the clipboard into the input
+    Paste,
+    /// Copies the selected match to the clipboard, e.g. the path of a file
+    Copy,
+    /// Switches to the next mode, when the launcher was started with multiple modes
+    NextMode,
+    PreviousMode,
+}
+
+/// A key together with the modifiers that must be held, written like `ctrl+shift+k`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub modifiers: ModifiersState,
+    pub key: KeyCode,
+}
+
+impl KeyChord {
+    pub fn new(modifiers: ModifiersState, key: KeyCode) -> Self {
+        Self { modifiers, key }
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lowercase = s.trim().to_lowercase();
+        // the key is always the last part, everything before it must be a modifier
+        let (modifier_names, key_name) = match lowercase.rsplit_once('+') {
+            Some((modifiers, key)) => (modifiers.split('+').collect(), key),
+            None => (Vec::new(), lowercase.as_str()),
+        };
+        let mut modifiers = ModifiersState::empty();
+        for name in modifier_names {
+            let modifier = match name {
+                "ctrl" | "control" => ModifiersState::CONTROL,
+                "shift" => ModifiersState::SHIFT,
+                "alt" => ModifiersState::ALT,
+                "super" | "meta" | "logo" => ModifiersState::SUPER,
+                _ => bail!("unknown modifier '{name}', expected ctrl, shift, alt or super"),
+            };
+            if modifiers.contains(modifier) {
+                bail!("modifier '{name}' is used twice");
+            }
+            modifiers |= modifier;
+        }
+        let key = parse_key(key_name).ok_or_else(|| anyhow!("unknown key '{key_name}'"))?;
+        Ok(Self { modifiers, key })
+    }
+}
+
+fn parse_key(name: &str) -> Option<KeyCode> {
+    let key = match name {
+        "enter" | "return" => KeyCode::Enter,
+        "escape" | "esc" => KeyCode::Escape,
+        "tab" => KeyCode::Tab,
+        "space" => KeyCode::Space,
+        "backspace" => KeyCode::Backspace,
+        "delete" | "del" => KeyCode::Delete,
+        "insert" => KeyCode::Insert,
+        "up" => KeyCode::ArrowUp,
+        "down" => KeyCode::ArrowDown,
+        "left" => KeyCode::ArrowLeft,
+        "right" => KeyCode::ArrowRight,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "page-up" | "pageup" => KeyCode::PageUp,
+        "page-down" | "pagedown" => KeyCode::PageDown,
+        "minus" => KeyCode::Minus,
+        "equal" => KeyCode::Equal,
+        "comma" => KeyCode::Comma,
+        "period" => KeyCode::Period,
+        "slash" => KeyCode::Slash,
+        "semicolon" => KeyCode::Semicolon,
+        "a" => KeyCode::KeyA,
+        "b" => KeyCode::KeyB,
+        "c" => KeyCode::KeyC,
+        "d" => KeyCode::KeyD,
+        "e" => KeyCode::KeyE,
+        "f" => KeyCode::KeyF,
+        "g" => KeyCode::KeyG,
+        "h" => KeyCode::KeyH,
+        "i" => KeyCode::KeyI,
+        "j" => KeyCode::KeyJ,
+        "k" => KeyCode::KeyK,
+        "l" => KeyCode::KeyL,
+        "m" => KeyCode::KeyM,
+        "n" => KeyCode::KeyN,
+        "o" => KeyCode::KeyO,
+        "p" => KeyCode::KeyP,
+        "q" => KeyCode::KeyQ,
+        "r" => KeyCode::KeyR,
+        "s" => KeyCode::KeyS,
+        "t" => KeyCode::KeyT,
+        "u" => KeyCode::KeyU,
+        "v" => KeyCode::KeyV,
+        "w" => KeyCode::KeyW,
+        "x" => KeyCode::KeyX,
+        "y" => KeyCode::KeyY,
+        "z" => KeyCode::KeyZ,
+        "0" => KeyCode::Digit0,
+        "1" => KeyCode::Digit1,
+        "2" => KeyCode::Digit2,
+        "3" => KeyCode::Digit3,
+        "4" => KeyCode::Digit4,
+        "5" => KeyCode::Digit5,
+        "6" => KeyCode::Digit6,
+        "7" => KeyCode::Digit7,
+        "8" => KeyCode::Digit8,
+        "9" => KeyCode::Digit9,
+        "f1" => KeyCode::F1,
+        "f2" => KeyCode::F2,
+        "f3" => KeyCode::F3,
+        "f4" => KeyCode::F4,
+        "f5" => KeyCode::F5,
+        "f6" => KeyCode::F6,
+        "f7" => KeyCode::F7,
+        "f8" => KeyCode::F8,
+        "f9" => KeyCode::F9,
+        "f10" => KeyCode::F10,
+        "f11" => KeyCode::F11,
+        "f12" => KeyCode::F12,
+        _ => return None,
+    };
+    Some(key)
+}
+
+/// The `[keybindings]` section of the config, maps key chords to actions like
+/// `"ctrl+j" = "select-next"`
+/// The configured bindings are added to the default bindings, replacing the defaults of the same
+/// key chords
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "HashMap<String, KeyAction>")]
+pub struct Keybindings {
+    bindings: HashMap<KeyChord, KeyAction>,
+}
+
+impl Keybindings {
+    pub fn get(&self, modifiers: ModifiersState, key: KeyCode) -> Option<KeyAction> {
+        self.bindings.get(&KeyChord::new(modifiers, key)).copied()
+    }
+
+    fn from_config(config: HashMap<String, KeyAction>) -> anyhow::Result<Self> {
+        let mut keybindings = Self::default();
+        let mut configured = HashMap::new();
+        for (name, action) in config {
+            let chord: KeyChord = name
+                .parse()
+                .with_context(|| format!("invalid keybinding '{name}'"))?;
+            // different spellings of the same chord, like `ctrl+j` and `control+J`
+            if let Some(other) = configured.insert(chord, name.clone()) {
+                bail!("keybindings '{other}' and '{name}' are the same key chord");
+            }
+            keybindings.bindings.insert(chord, action);
+        }
+        Ok(keybindings)
+    }
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        let bindings = [
+            ("escape", KeyAction::Close),
+            ("ctrl+c", KeyAction::Close),
+            ("enter", KeyAction::Accept),
+            // holding ctrl keeps the launcher open
+            ("ctrl+enter", KeyAction::Accept),
+            ("shift+enter", KeyAction::AcceptCustom),
+            ("down", KeyAction::SelectNext),
+            ("ctrl+j", KeyAction::SelectNext),
+            ("up", KeyAction::SelectPrevious),
+            ("ctrl+k", KeyAction::SelectPrevious),
+            ("page-down", KeyAction::PageDown),
+            ("page-up", KeyAction::PageUp),
+            ("ctrl+home", KeyAction::SelectFirst),
+            ("ctrl+end", KeyAction::SelectLast),
+            ("ctrl+space", KeyAction::ToggleMark),
+            ("ctrl+delete", KeyAction::Forget),
+            ("ctrl+p", KeyAction::TogglePin),
+            ("ctrl+r", KeyAction::CycleMatching),
+            ("ctrl+w", KeyAction::DeleteWord),
+            ("ctrl+backspace", KeyAction::DeleteWord),
+            ("alt+backspace", KeyAction::DeleteWord),
+            ("ctrl+u", KeyAction::KillLine),
+            ("ctrl+y", KeyAction::Yank),
+            ("ctrl+a", KeyAction::LineStart),
+            ("ctrl+e", KeyAction::LineEnd),
+            ("alt+b", KeyAction::WordLeft),
+            ("alt+f", KeyAction::WordRight),
+            ("ctrl+z", KeyAction::Undo),
+            ("ctrl+shift+z", KeyAction::Redo),
+            ("ctrl+v", KeyAction::Paste),
+            ("shift+insert", KeyAction::Paste),
+            ("ctrl+shift+c", KeyAction::Copy),
+            ("tab", KeyAction::NextMode),
+            ("shift+tab", KeyAction::PreviousMode),
+        ];
+        Self {
+            bindings: bindings
+                .into_iter()
+                .map(|(chord, action)| (chord.parse().unwrap(), action))
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<HashMap<String, KeyAction>> for Keybindings {
+    type Error = String;
+
+    fn try_from(config: HashMap<String, KeyAction>) -> Result<Self, Self::Error> {
+        // include the cause in the message, serde only shows the error itself
+        Self::from_config(config).map_err(|e| format!("{e:#}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(toml: &str) -> Result<Keybindings, toml::de::Error> {
+        toml::from_str(toml)
+    }
+
+    #[test]
+    fn test_parse_chord() {
+        let chord: KeyChord = "Ctrl+Shift+k".parse().unwrap();
+        assert_eq!(
+            chord,
+            KeyChord::new(
+                ModifiersState::CONTROL | ModifiersState::SHIFT,
+                KeyCode::KeyK
+            )
+        );
+        let chord: KeyChord = "page-down".parse().unwrap();
+        assert_eq!(
+            chord,
+            KeyChord::new(ModifiersState::empty(), KeyCode::PageDown)
+        );
+    }
+
+    #[test]
+    fn test_config_overrides_defaults() {
+        let keybindings =
+            parse("\"ctrl+n\" = \"select-next\"\n\"ctrl+k\" = \"delete-word\"").unwrap();
+        let ctrl = ModifiersState::CONTROL;
+        assert_eq!(
+            keybindings.get(ctrl, KeyCode::KeyN),
+            Some(KeyAction::SelectNext)
+        );
+        assert_eq!(
+            keybindings.get(ctrl, KeyCode::KeyK),
+            Some(KeyAction::DeleteWord)
+        );
+        assert_eq!(
+            keybindings.get(ctrl, KeyCode::KeyJ),
+            Some(KeyAction::SelectNext)
+        );
+    }
+
+    #[test]
+    fn test_invalid_bindings() {
+        let error = parse("\"ctrl+foo\" = \"accept\"").unwrap_err().to_string();
+        assert!(error.contains("invalid keybinding 'ctrl+foo'"), "{error}");
+        let error = parse("\"hyper+a\" = \"accept\"").unwrap_err().to_string();
+        assert!(error.contains("unknown modifier 'hyper'"), "{error}");
+        let error = parse("\"ctrl+a\" = \"fly\"").unwrap_err().to_string();
+        assert!(error.contains("unknown variant `fly`"), "{error}");
+        let error = parse("\"ctrl+a\" = \"accept\"\n\"control+A\" = \"close\"")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("same key chord"), "{error}");
+    }
+}
@@ -1,61 +1,171 @@
+pub use crate::ranking::Match;
 use crate::{
+    calc,
+    clipboard::Clipboard,
     config::Config,
-    item::Item,
-    mode::{self, Mode},
+    item::{Action, Item},
+    keybindings::KeyAction,
+    mode::{Matching, Mode, Status},
+    ranking::Ranker,
     recent::RecentItems,
     ui::{
-        DynWidget, DynamicList, Editor, Length, TextBuilder, TextEditor, UVec2, Widget, column,
-        container,
+        DynWidget, DynamicList, DynamicText, Editor, Length, Rect, SpanStyle, Text, TextBuilder,
+        TextEditor, UVec2, Widget, column, container,
     },
     winit_app::EventHandle,
 };
-use anyhow::Context;
-use cosmic_text::Action;
+use cosmic_text::Motion;
 use indexmap::IndexSet;
-use std::hash::{Hash, Hasher};
+use std::{ops::Range, sync::Arc};
+use unicode_segmentation::UnicodeSegmentation;
 use winit::{
-    event::{ElementState, KeyEvent},
-    keyboard::{KeyCode, PhysicalKey},
+    event::{ElementState, Ime, KeyEvent, MouseButton, MouseScrollDelta},
+    keyboard::{ModifiersState, PhysicalKey},
 };
 
-const MAX_RECENT_DISPLAY: usize = 8;
+/// Number of matches that are fetched from the mode at once, more are fetched when scrolling
+pub const PAGE_SIZE: usize = 64;
+/// Space around the content of the launcher, clicking it closes the launcher
+const CONTENT_PADDING: u32 = 32;
+// NOTE: due to limitations of the layout system, the item height must be large enough to fit the text
+const ITEM_HEIGHT: u32 = 28;
+const ITEM_SPACING: u32 = 8;
 
-pub struct Launcher {
+/// A mode the launcher can switch to, it keeps its own query while another mode is active
+struct ModeTab {
     mode: Box<dyn Mode>,
+    query: String,
+    selected: usize,
+    matching: Matching,
+}
+
+pub struct Launcher {
+    tabs: Vec<ModeTab>,
+    /// Index of the active tab
+    active: usize,
     root: DynWidget,
+    header: DynamicText,
+    /// Index of the selected match
     selected: usize,
+    /// Maximum number of matches to fetch from the mode
+    limit: usize,
+    /// The input of the last update, the selection is reset when it changes
+    input: String,
     config: Config,
     close_requested: bool,
-    ctrl_pressed: bool,
+    modifiers: ModifiersState,
     recent: RecentItems,
     list: DynamicList,
-    matches: IndexSet<Match>,
+    matches: Vec<Match>,
+    /// Not available when there is no clipboard, e.g. without a display server
+    clipboard: Option<Box<dyn Clipboard>>,
+    /// Marked items, which are all executed when accepting
+    marked: IndexSet<Arc<Item>>,
     editor: Editor,
+    status: DynamicText,
+    /// The text currently shown by the status line, used to only rebuild it when it changes
+    status_text: String,
+    size: UVec2,
+    cursor: Option<UVec2>,
+    /// Index of the match under the mouse cursor
+    hovered: Option<usize>,
+    /// Scrolled pixels that did not add up to a whole item yet
+    scroll_pixels: f64,
 }
 
 impl Launcher {
-    pub fn load(mode: Box<dyn Mode>) -> anyhow::Result<Self> {
-        let config = Config::load().context("failed to load config")?;
+    /// Loads the launcher with one or more modes, `active` is the index of the initial mode
+    /// The clipboard is `None` when there is none, e.g. without a display server
+    pub fn load(
+        config: Config,
+        modes: Vec<Box<dyn Mode>>,
+        active: usize,
+        recent: RecentItems,
+        clipboard: Option<Box<dyn Clipboard>>,
+    ) -> Self {
+        assert!(
+            active < modes.len(),
+            "the initial mode must be one of the modes"
+        );
         let editor = Editor::new(config.font.font_name.clone());
-        // NOTE: due to limitations of the layout system, the item height must be large enough to fit the text
-        let list = DynamicList::new(28, 8);
-        let root = build_ui(mode.display_name(), &config, editor.clone(), list.clone());
-        Ok(Self {
+        let list =
+            DynamicList::new(ITEM_HEIGHT, ITEM_SPACING).scrollbar(config.color.foreground_second);
+        let tabs: Vec<ModeTab> = modes
+            .into_iter()
+            .map(|mode| ModeTab {
+                matching: mode_matching(mode.as_ref(), config.matching),
+                mode,
+                query: String::new(),
+                selected: 0,
+            })
+            .collect();
+        let header = DynamicText::new(build_header(&tabs, active, &config));
+        let status = DynamicText::new(build_status("0/0", &config));
+        let root = build_ui(
+            &config,
+            header.clone(),
+            editor.clone(),
+            list.clone(),
+            status.clone(),
+        );
+        Self {
             root,
-            mode,
+            header,
+            tabs,
+            active,
             selected: 0,
+            limit: PAGE_SIZE,
+            input: String::new(),
             config,
             close_requested: false,
-            ctrl_pressed: false,
-            recent: RecentItems::load_or_default()?,
+            modifiers: ModifiersState::empty(),
+            recent,
             list,
-            matches: IndexSet::new(),
+            matches: Vec::new(),
+            marked: IndexSet::new(),
+            clipboard,
             editor,
-        })
+            status,
+            status_text: String::new(),
+            size: UVec2::ZERO,
+            cursor: None,
+            hovered: None,
+            scroll_pixels: 0.0,
+        }
     }
 
+    /// Runs all modes, so they load their items in the background
     pub fn run(&mut self, event_handle: EventHandle) {
-        self.mode.run(event_handle);
+        for tab in &mut self.tabs {
+            tab.mode.run(event_handle.clone());
+        }
+    }
+
+    fn mode(&self) -> &dyn Mode {
+        self.tabs[self.active].mode.as_ref()
+    }
+
+    /// Switches to the mode at the offset from the active mode, wrapping around at the ends,
+    /// returns whether there was another mode to switch to
+    fn switch_mode(&mut self, offset: isize) -> bool {
+        if self.tabs.len() < 2 {
+            return false;
+        }
+        let tab = &mut self.tabs[self.active];
+        tab.query = self.editor.text();
+        tab.selected = self.selected;
+        self.active = (self.active as isize + offset).rem_euclid(self.tabs.len() as isize) as usize;
+
+        let tab = &self.tabs[self.active];
+        self.editor.set_text(&tab.query);
+        self.input = tab.query.clone();
+        self.selected = tab.selected;
+        self.limit = PAGE_SIZE.max(tab.selected + PAGE_SIZE);
+        // the marked items belong to the previous mode
+        self.marked.clear();
+        self.header
+            .set(build_header(&self.tabs, self.active, &self.config));
+        true
     }
 
     pub fn root(&self) -> &DynWidget {
@@ -63,155 +173,513 @@ impl Launcher {
     }
 
     pub fn resize(&mut self, size: UVec2) {
+        self.size = size;
         self.root.layout(size);
     }
 
-    pub fn key_input(&mut self, event: KeyEvent) -> bool {
+    /// Highlights the match under the cursor
+    pub fn cursor_moved(&mut self, position: UVec2) -> bool {
+        self.cursor = Some(position);
+        let hovered = self.list.item_at(position);
+        let is_dirty = hovered != self.hovered;
+        self.hovered = hovered;
+        is_dirty
+    }
+
+    pub fn cursor_left(&mut self) -> bool {
+        self.cursor = None;
+        self.hovered.take().is_some()
+    }
+
+    /// Clicking a match launches it, clicking outside of the content closes the launcher
+    pub fn mouse_input(&mut self, state: ElementState, button: MouseButton) -> bool {
+        if state != ElementState::Pressed || button != MouseButton::Left {
+            return false;
+        }
+        let Some(cursor) = self.cursor else {
+            return false;
+        };
+        if let Some(index) = self.list.item_at(cursor) {
+            self.select(index);
+            return self.launch_selected();
+        }
+        let outside = cursor.x < CONTENT_PADDING
+            || cursor.y < CONTENT_PADDING
+            || cursor.x >= self.size.x.saturating_sub(CONTENT_PADDING)
+            || cursor.y >= self.size.y.saturating_sub(CONTENT_PADDING);
+        if outside {
+            self.close_requested = true;
+        }
+        false
+    }
+
+    /// Moves the selection by one match for every scrolled line
+    pub fn mouse_wheel(&mut self, delta: MouseScrollDelta) -> bool {
+        let lines = match delta {
+            MouseScrollDelta::LineDelta(_, y) => y as i64,
+            MouseScrollDelta::PixelDelta(position) => {
+                self.scroll_pixels += position.y;
+                let item_size = (ITEM_HEIGHT + ITEM_SPACING) as f64;
+                let lines = (self.scroll_pixels / item_size).trunc();
+                self.scroll_pixels -= lines * item_size;
+                lines as i64
+            }
+        };
+        if lines == 0 {
+            return false;
+        }
+        // scrolling up gives a positive delta
+        self.select(self.selected.saturating_add_signed(-lines as isize));
+        true
+    }
+
+    pub fn modifiers_changed(&mut self, modifiers: ModifiersState) {
+        self.modifiers = modifiers;
+    }
+
+    pub fn key_input(&mut self, event: &KeyEvent) -> bool {
+        if event.state != ElementState::Pressed {
+            return false;
+        }
+        if let PhysicalKey::Code(key) = event.physical_key
+            && let Some(action) = self.config.keybindings.get(self.modifiers, key)
+        {
+            log::debug!("key action: {action:?}");
+            return self.perform(action);
+        }
+        // Editor input
         let mut is_dirty = false;
-        if event.state == ElementState::Pressed {
-            if event.physical_key == PhysicalKey::Code(KeyCode::Escape) {
+        if let PhysicalKey::Code(key) = event.physical_key {
+            is_dirty = self.editor.handle_key(key, self.modifiers);
+        }
+        // the text can contain multiple characters, e.g. from a compose sequence
+        if let Some(text) = &event.text
+            && !is_dirty
+        {
+            is_dirty = self.editor.insert(text);
+        }
+        is_dirty
+    }
+
+    /// Handles text composed by an input method, e.g. for CJK input
+    pub fn ime_input(&mut self, ime: &Ime) -> bool {
+        match ime {
+            Ime::Preedit(text, cursor) => self.editor.set_preedit(text, *cursor),
+            Ime::Commit(text) => self.editor.commit(text),
+            Ime::Disabled => return self.editor.clear_preedit(),
+            Ime::Enabled => return false,
+        }
+        true
+    }
+
+    /// The area of the input cursor on the screen
+    pub fn ime_cursor_area(&self) -> Option<Rect> {
+        self.editor.cursor_area()
+    }
+
+    /// Performs an action that is bound to a key, returns whether the UI should be redrawn
+    fn perform(&mut self, action: KeyAction) -> bool {
+        match action {
+            KeyAction::SelectNext => {
+                // wrap around at the end of the list
+                let next = self.selected + 1;
+                self.select(if next < self.matches.len() { next } else { 0 });
+            }
+            KeyAction::SelectPrevious => {
+                let previous = self.selected.checked_sub(1);
+                self.select(previous.unwrap_or(self.matches.len().saturating_sub(1)));
+            }
+            KeyAction::PageDown => self.select(self.selected + self.list.max_items()),
+            KeyAction::PageUp => self.select(self.selected.saturating_sub(self.list.max_items())),
+            KeyAction::SelectFirst => {
+                // the matches that were fetched to scroll down are no longer needed
+                self.limit = PAGE_SIZE;
+                self.select(0);
+            }
+            KeyAction::SelectLast => {
+                // fetch the matches that are known now to find the last one, the update selects it
+                self.limit = self.limit.max(self.mode().status().matched as usize);
+                self.selected = usize::MAX;
+            }
+            KeyAction::Accept => return self.launch_selected(),
+            KeyAction::AcceptCustom => return self.launch_custom(),
+            KeyAction::ToggleMark => self.toggle_mark_selected(),
+            KeyAction::Close => {
                 self.close_requested = true;
-            } else if event.physical_key == PhysicalKey::Code(KeyCode::Enter) {
-                // holding CTRL keeps the launchr open
-                if !self.ctrl_pressed {
-                    self.close_requested = true;
-                } else {
-                    is_dirty = true; // the UI should be redrawn when it is kept open
-                }
-                if let Some(cache_key) = self.mode.cache_key() {
-                    if let Err(e) = self
-                        .recent
-                        .insert_and_save(cache_key, self.matches[self.selected].item.clone())
-                    {
-                        log::error!("Failed to cache recent items: {e}");
-                    }
-                }
-                // Execute the selected match
-                self.matches[self.selected].item.exec();
-            } else if event.physical_key == PhysicalKey::Code(KeyCode::ArrowDown)
-                || self.ctrl_pressed && event.physical_key == PhysicalKey::Code(KeyCode::KeyJ)
-            {
-                let list_length = self.list.max_items().min(self.matches.len());
-                self.selected = (self.selected as i64 + 1).rem_euclid(list_length as i64) as usize;
-                log::info!("selected: {}", self.selected);
-                is_dirty = true;
-            } else if event.physical_key == PhysicalKey::Code(KeyCode::ArrowUp)
-                || self.ctrl_pressed && event.physical_key == PhysicalKey::Code(KeyCode::KeyK)
+                return false;
+            }
+            KeyAction::Forget => self.forget_selected(),
+            KeyAction::TogglePin => self.toggle_pin_selected(),
+            KeyAction::CycleMatching => self.cycle_matching(),
+            KeyAction::DeleteWord => self.editor.delete_word(),
+            KeyAction::KillLine => self.editor.kill_line(),
+            KeyAction::Yank => self.editor.yank(),
+            KeyAction::LineStart => self.editor.move_cursor(Motion::Home, false),
+            KeyAction::LineEnd => self.editor.move_cursor(Motion::End, false),
+            KeyAction::WordLeft => self.editor.move_cursor(Motion::PreviousWord, false),
+            KeyAction::WordRight => self.editor.move_cursor(Motion::NextWord, false),
+            KeyAction::Undo => self.editor.undo(),
+            KeyAction::Redo => self.editor.redo(),
+            KeyAction::Paste => self.paste(),
+            KeyAction::NextMode => return self.switch_mode(1),
+            KeyAction::PreviousMode => return self.switch_mode(-1),
+            KeyAction::Copy => self.copy_selected(),
+        }
+        true
+    }
+
+    /// Executes the marked matches, or the selected match if none are marked, returns whether
+    /// the UI should be redrawn
+    /// Items with text to copy are copied to the clipboard, which keeps the text after the
+    /// launcher exits
+    fn launch_selected(&mut self) -> bool {
+        let items: Vec<Arc<Item>> = if self.marked.is_empty() {
+            self.matches
+                .get(self.selected)
+                .map(|r#match| r#match.item.clone())
+                .into_iter()
+                .collect()
+        } else {
+            self.marked.drain(..).collect()
+        };
+        for item in items {
+            if let Some(cache_key) = self.tabs[self.active].mode.cache_key()
+                && !self.is_inline_calc(&item)
+                && let Err(e) =
+                    self.recent
+                        .insert_and_save(cache_key, item.clone(), &self.editor.text())
             {
-                let list_length = self.list.max_items().min(self.matches.len());
-                self.selected = (self.selected as i64 - 1).rem_euclid(list_length as i64) as usize;
-                log::info!("selected: {}", self.selected);
-                is_dirty = true;
-            } else if event.physical_key == PhysicalKey::Code(KeyCode::ControlLeft)
-                || event.physical_key == PhysicalKey::Code(KeyCode::ControlRight)
+                log::error!("Failed to cache recent items: {e}");
+            }
+            if matches!(item.action, Action::Copy { .. })
+                && let Some(clipboard) = self.clipboard.as_deref_mut()
             {
-                self.ctrl_pressed = true;
-            } else if self.ctrl_pressed && event.physical_key == PhysicalKey::Code(KeyCode::KeyC) {
-                self.close_requested = true;
-            } else {
-                // Editor input
-                if let PhysicalKey::Code(key) = event.physical_key {
-                    is_dirty = self.editor.handle_key(key);
-                }
-                if let Some(char) = event.text.and_then(|t| t.chars().next()) {
-                    self.editor.perform_action(Action::Insert(char));
-                    self.selected = 0;
-                    is_dirty = true;
+                match clipboard.set_text(&item.copy_text()) {
+                    Ok(()) => continue,
+                    Err(e) => log::error!("Failed to copy to the clipboard: {e}"),
                 }
             }
-        } else if event.state == ElementState::Released
-            && (event.physical_key == PhysicalKey::Code(KeyCode::ControlLeft)
-                || event.physical_key == PhysicalKey::Code(KeyCode::ControlRight))
+            // without a clipboard the text is printed
+            item.exec();
+        }
+        self.close_unless_ctrl()
+    }
+
+    /// Whether the item is the result of a calculation shown inside the mode, which is not worth
+    /// remembering in its history
+    fn is_inline_calc(&self, item: &Item) -> bool {
+        self.matches.first().is_some_and(|r#match| {
+            r#match.source.as_deref() == Some(calc::INLINE_SOURCE) && *r#match.item == *item
+        })
+    }
+
+    /// Executes the input itself, if the mode supports it
+    fn launch_custom(&mut self) -> bool {
+        let Some(item) = self.mode().custom_item(&self.editor.text()) else {
+            return false;
+        };
+        item.exec();
+        self.close_unless_ctrl()
+    }
+
+    /// Holding CTRL keeps the launcher open after launching, returns whether the UI should be
+    /// redrawn because it is kept open
+    fn close_unless_ctrl(&mut self) -> bool {
+        if !self.modifiers.control_key() {
+            self.close_requested = true;
+        }
+        !self.close_requested
+    }
+
+    fn paste(&mut self) {
+        if let Some(clipboard) = self.clipboard.as_deref_mut()
+            && let Err(e) = self.editor.paste(clipboard)
+        {
+            log::error!("Failed to paste from the clipboard: {e}");
+        }
+    }
+
+    /// Copies the selected match, the launcher stays open
+    fn copy_selected(&mut self) {
+        if let Some(clipboard) = self.clipboard.as_deref_mut()
+            && let Some(r#match) = self.matches.get(self.selected)
+            && let Err(e) = clipboard.set_text(&r#match.item.copy_text())
+        {
+            log::error!("Failed to copy to the clipboard: {e}");
+        }
+    }
+
+    /// Marks or unmarks the selected match and selects the next one
+    fn toggle_mark_selected(&mut self) {
+        if let Some(r#match) = self.matches.get(self.selected) {
+            if !self.marked.shift_remove(&r#match.item) {
+                self.marked.insert(r#match.item.clone());
+            }
+            self.select(self.selected + 1);
+        }
+    }
+
+    /// Selects the match at the index, limited to the fetched matches
+    /// More matches are fetched on the next update when the selection gets close to the end
+    fn select(&mut self, index: usize) {
+        self.selected = index.min(self.matches.len().saturating_sub(1));
+        log::debug!("selected: {}", self.selected);
+        let page = self.list.max_items().max(1);
+        if self.selected + page >= self.limit {
+            self.limit = self.limit.saturating_add(PAGE_SIZE.max(page));
+        }
+    }
+
+    /// Removes the selected item from the history of the mode
+    fn forget_selected(&mut self) {
+        if let Some(cache_key) = self.tabs[self.active].mode.cache_key()
+            && let Some(r#match) = self.matches.get(self.selected)
+            && let Err(e) = self.recent.forget_and_save(cache_key, &r#match.item)
         {
-            self.ctrl_pressed = false;
+            log::error!("Failed to forget recent item: {e}");
         }
-        is_dirty
+    }
+
+    fn toggle_pin_selected(&mut self) {
+        if let Some(cache_key) = self.tabs[self.active].mode.cache_key()
+            && let Some(r#match) = self.matches.get(self.selected)
+            && let Err(e) =
+                self.recent
+                    .set_pinned_and_save(cache_key, r#match.item.clone(), !r#match.pinned)
+        {
+            log::error!("Failed to pin recent item: {e}");
+        }
+    }
+
+    /// Switches to the next kind of matching, the status line shows the current kind
+    fn cycle_matching(&mut self) {
+        let matching = &mut self.tabs[self.active].matching;
+        matching.kind = matching.kind.next();
+        self.selected = 0;
     }
 
     pub fn update(&mut self) {
         let input = self.editor.text();
-        self.matches.clear();
-        if let Some(cache_key) = self.mode.cache_key() {
-            let recent_items = self.recent.get_items(cache_key);
-            for item in mode::fuzzy_match(&input, &recent_items)
-                .into_iter()
-                .take(MAX_RECENT_DISPLAY)
-            {
-                self.matches.insert(Match { item, recent: true });
-            }
+        if input != self.input {
+            self.selected = 0;
+            self.limit = PAGE_SIZE;
+            self.input = input;
         }
+        let tab = &mut self.tabs[self.active];
+        self.matches = find_matches(
+            tab.mode.as_mut(),
+            &self.recent,
+            &self.input,
+            tab.matching,
+            self.limit,
+        );
+        self.selected = self.selected.min(self.matches.len().saturating_sub(1));
+        let status = self.mode().status();
+        self.update_status(status);
 
-        for item in self.mode.update(&input) {
-            self.matches.insert({
-                Match {
-                    item,
-                    recent: false,
-                }
-            });
-        }
+        // the scrollbar also counts the matches that were not fetched yet
+        let total = if self.matches.len() < self.limit {
+            self.matches.len()
+        } else {
+            (status.matched as usize).max(self.matches.len())
+        };
+        self.list.scroll_to(self.selected, total);
+        // the match under the cursor changes when the list scrolls
+        self.hovered = self.cursor.and_then(|cursor| self.list.item_at(cursor));
+        self.rebuild_list();
+    }
 
+    /// Rebuilds the visible matches without fetching them again, e.g. when only the hovered match
+    /// changed
+    pub fn rebuild_list(&mut self) {
+        let visible = self.matches.iter().enumerate().skip(self.list.offset());
         self.list
-            .update(self.matches.iter().enumerate().map(|(i, r#match)| {
-                let item_text = format!(
-                    "{}  {}",
-                    if r#match.recent { '' } else { ' ' },
-                    r#match.item
+            .update(visible.take(self.list.max_items()).map(|(i, r#match)| {
+                let prefix = format!(
+                    "{}  ",
+                    if self.marked.contains(&r#match.item) {
+                        ''
+                    } else if r#match.pinned {
+                        ''
+                    } else if r#match.recent {
+                        ''
+                    } else {
+                        ' '
+                    }
                 );
+                let item_text = format!("{prefix}{}", r#match.item);
+                let text_offset = prefix.len() + r#match.item.display_text_offset();
+
+                let mut text = TextBuilder::new(&item_text)
+                    .size(self.config.font.normal_size)
+                    .font(self.config.font.font_name.as_ref())
+                    .bold(i == self.selected);
+                let highlight = SpanStyle::new().color(self.config.color.accent).bold();
+                for range in byte_ranges(&r#match.item.text, &r#match.indices) {
+                    text = text.span(
+                        range.start + text_offset..range.end + text_offset,
+                        highlight.clone(),
+                    );
+                }
+                if let Some(source) = &r#match.source {
+                    let tag = SpanStyle::new().color(self.config.color.foreground_second);
+                    text = text.append(&format!("  {source}"), tag);
+                }
 
-                container(
-                    TextBuilder::new(&item_text)
-                        .size(self.config.font.normal_size)
-                        .font(self.config.font.font_name.as_ref())
-                        .bold(i == self.selected)
-                        .build(),
-                )
-                .bg(if i == self.selected {
-                    self.config.color.primary
-                } else {
-                    self.config.color.background
-                })
-                .width(Length::Fill)
-                .padding((4, 8)) // must fit within the list item height
-                .into_dyn()
+                container(text.build())
+                    .bg(if i == self.selected {
+                        self.config.color.primary
+                    } else if Some(i) == self.hovered {
+                        self.config.color.background_second
+                    } else {
+                        self.config.color.background
+                    })
+                    .width(Length::Fill)
+                    .padding((4, 8)) // must fit within the list item height
+                    .into_dyn()
             }));
     }
 
+    /// Shows the number of matched and loaded items like `12/34567`, together with the kind of
+    /// matching and whether the mode is still loading
+    fn update_status(&mut self, status: Status) {
+        let mut text = format!(
+            "{}/{} · {}",
+            status.matched,
+            status.total,
+            self.tabs[self.active].matching.kind.name()
+        );
+        if !self.marked.is_empty() {
+            text.push_str(&format!(" · {} marked", self.marked.len()));
+        }
+        if status.loading {
+            text.push_str(" · loading");
+        }
+        if text != self.status_text {
+            self.status.set(build_status(&text, &self.config));
+            self.status_text = text;
+        }
+    }
+
     pub fn close_requested(&self) -> bool {
         self.close_requested
     }
 }
 
-struct Match {
-    item: Item,
-    recent: bool,
+/// The matching of the config, with the kind replaced by the one of the mode if it has one
+pub fn mode_matching(mode: &dyn Mode, matching: Matching) -> Matching {
+    Matching {
+        kind: mode.match_kind().unwrap_or(matching.kind),
+        ..matching
+    }
 }
 
-impl PartialEq for Match {
-    fn eq(&self, other: &Self) -> bool {
-        self.item == other.item
+/// Matches the input against the recent items and the items of the mode
+/// The candidates of both sources are ranked together, see [`Ranker`]
+/// When the mode shows calculations inline, the result of the input comes first
+pub fn find_matches(
+    mode: &mut dyn Mode,
+    recent: &RecentItems,
+    input: &str,
+    matching: Matching,
+    limit: usize,
+) -> Vec<Match> {
+    let mut ranker = Ranker::new(recent, mode.cache_key(), input).keep_order(mode.keeps_order());
+    for scored in mode.update(input, matching, limit) {
+        ranker.add(scored);
     }
+    if let Some(cache_key) = mode.cache_key()
+        && !mode.keeps_order()
+        && let Some(input) = mode.history_query(input)
+    {
+        for scored in matching
+            .query(input)
+            .match_list(&recent.get_items(cache_key))
+        {
+            ranker.add(scored);
+        }
+    }
+    let mut matches = ranker.finish();
+    if mode.inline_calc()
+        && let Ok(answer) = calc::evaluate(input)
+        && !answer.trivial
+    {
+        matches.insert(
+            0,
+            Match {
+                item: Arc::new(Item::new(
+                    answer.text.clone(),
+                    Action::Copy { text: answer.text },
+                )),
+                score: u32::MAX,
+                indices: Vec::new(),
+                recent: false,
+                pinned: false,
+                source: Some(calc::INLINE_SOURCE.into()),
+            },
+        );
+    }
+    matches
+}
+
+/// Converts sorted grapheme indices into contiguous byte ranges of the text
+/// Nucleo matches a single character per grapheme, so an emoji with a skin tone or a letter with
+/// a combining accent counts as one index
+fn byte_ranges(text: &str, indices: &[u32]) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    let mut indices = indices.iter().peekable();
+    for (i, (start, grapheme)) in text.grapheme_indices(true).enumerate() {
+        if indices.next_if(|&&index| index as usize == i).is_none() {
+            continue;
+        }
+        let end = start + grapheme.len();
+        match ranges.last_mut() {
+            Some(range) if range.end == start => range.end = end,
+            _ => ranges.push(start..end),
+        }
+    }
+    ranges
 }
 
-impl Eq for Match {}
+fn build_status(text: &str, config: &Config) -> Text {
+    TextBuilder::new("")
+        .append(text, SpanStyle::new().color(config.color.foreground_second))
+        .size(config.font.normal_size)
+        .font(config.font.font_name.as_ref())
+        .build()
+}
 
-impl Hash for Match {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.item.hash(state);
+/// Shows the names of the modes as tabs, the active mode is highlighted
+fn build_header(tabs: &[ModeTab], active: usize, config: &Config) -> Text {
+    let mut text = TextBuilder::new("");
+    for (i, tab) in tabs.iter().enumerate() {
+        let style = if i == active {
+            SpanStyle::new().color(config.color.foreground).bold()
+        } else {
+            SpanStyle::new().color(config.color.foreground_second)
+        };
+        text = text.append(tab.mode.display_name(), style);
+        text = text.append("   ", SpanStyle::new());
     }
+    text.append(
+        &format!("launchr v{}", crate::VERSION),
+        SpanStyle::new().color(config.color.foreground_second),
+    )
+    .size(config.font.large_size)
+    .font(config.font.font_name.as_ref())
+    .build()
 }
 
-fn build_ui(mode_name: &str, config: &Config, editor: Editor, list: DynamicList) -> DynWidget {
+fn build_ui(
+    config: &Config,
+    header: DynamicText,
+    editor: Editor,
+    list: DynamicList,
+    status: DynamicText,
+) -> DynWidget {
     let editor = TextEditor::new(editor, config.font.normal_size);
     let root = container(column([
-        container(
-            TextBuilder::new(format!("{} (launchr v{})", mode_name, crate::VERSION))
-                .size(config.font.large_size)
-                .font(config.font.font_name.as_ref())
-                .bold(true)
-                .build(),
-        )
-        .padding((0, 4))
-        .into_dyn(),
+        container(header).padding((0, 4)).into_dyn(),
         container(
             container(editor)
                 .padding((4, 8))
@@ -219,6 +687,7 @@ fn build_ui(mode_name: &str, config: &Config, editor: Editor, list: DynamicList)
         )
         .padding((0, 8))
         .into_dyn(),
+        container(status).padding((8, 4)).into_dyn(),
         list.into_dyn(),
     ]))
     .padding_all(32)
@@ -227,3 +696,70 @@ fn build_ui(mode_name: &str, config: &Config, editor: Editor, list: DynamicList)
     .height(Length::Fill);
     root.into_dyn()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        clipboard::FakeClipboard,
+        headless::wait_until_loaded,
+        mode::{CalcMode, DmenuMode},
+    };
+
+    /// Loads a launcher with the mode and types the query, once the mode has loaded
+    fn launcher_with(mode: impl Mode + 'static, query: &str) -> (Launcher, FakeClipboard) {
+        let clipboard = FakeClipboard::default();
+        let mut launcher = Launcher::load(
+            Config::default(),
+            vec![Box::new(mode)],
+            0,
+            RecentItems::default(),
+            Some(Box::new(clipboard.clone())),
+        );
+        let (event_handle, events) = EventHandle::channel();
+        launcher.run(event_handle);
+        launcher.editor.insert(query);
+        let tab = &mut launcher.tabs[0];
+        wait_until_loaded(tab.mode.as_mut(), query, tab.matching, &events);
+        launcher.update();
+        (launcher, clipboard)
+    }
+
+    #[test]
+    fn test_copy_selected() {
+        let mode = DmenuMode::new(None, "firefox\nthunderbird\n".to_string());
+        let (mut launcher, clipboard) = launcher_with(mode, "thunder");
+        launcher.copy_selected();
+        assert_eq!(clipboard.text(), "thunderbird");
+        assert!(!launcher.close_requested());
+    }
+
+    #[test]
+    fn test_switch_mode_needs_another_mode() {
+        let mode = DmenuMode::new(None, "firefox\nthunderbird\n".to_string());
+        let (mut launcher, _) = launcher_with(mode, "fire");
+        launcher.toggle_mark_selected();
+        assert!(!launcher.switch_mode(1));
+        // the marked items are kept, they belong to the same mode
+        assert_eq!(launcher.marked.len(), 1);
+    }
+
+    #[test]
+    fn test_launch_copies_the_result() {
+        let (mut launcher, clipboard) = launcher_with(CalcMode::new(), "6 * 7");
+        assert!(!launcher.launch_selected());
+        assert!(launcher.close_requested());
+        // the clipboard was handed the text, so it is still available after the launcher exits
+        drop(launcher);
+        assert_eq!(clipboard.text(), "42");
+    }
+
+    #[test]
+    fn test_byte_ranges() {
+        assert_eq!(byte_ranges("firefox", &[0, 1, 2, 5]), vec![0..3, 5..6]);
+        assert_eq!(byte_ranges("héllo", &[1, 2]), vec![1..4]);
+        assert!(byte_ranges("foo", &[]).is_empty());
+        // the waving hand with a skin tone modifier is a single grapheme of two characters
+        assert_eq!(byte_ranges("👋🏿  waving", &[3, 4]), vec![10..12]);
+    }
+}
@@ -1,10 +1,15 @@
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+pub mod calc;
+pub mod clipboard;
 pub mod config;
 pub mod file_finder;
+pub mod headless;
 pub mod item;
+pub mod keybindings;
 pub mod launcher;
 pub mod mode;
+pub mod ranking;
 pub mod recent;
 pub mod render;
 pub mod ui;
@@ -1,7 +1,15 @@
-use clap::Parser;
+use anyhow::bail;
+use clap::{Parser, Subcommand};
 use launchr::{
+    clipboard::{self, Clipboard, SystemClipboard},
+    config::Config,
+    headless,
     launcher::Launcher,
-    mode::{AppsMode, DmenuMode, FilesMode, Mode, RunMode},
+    mode::{
+        AppsMode, CalcMode, CombiMode, CustomMode, DmenuMode, EmojiMode, FilesMode, Mode,
+        PluginMode, RunMode,
+    },
+    recent::RecentItems,
     winit_app::WinitApp,
 };
 use std::io::{Read, stdin};
@@ -15,9 +23,133 @@ struct Args {
     /// Prompt to display in dmenu mode
     #[arg(short, long)]
     prompt: Option<String>,
-    /// Mode to use
-    #[arg(short, long, default_value = "run")]
-    mode: String,
+    /// Modes to use, separated by commas, switch between them with Tab
+    #[arg(short, long, default_value = "run", value_delimiter = ',')]
+    mode: Vec<String>,
+    /// Mode to show first, defaults to the first mode
+    #[arg(short, long)]
+    show: Option<String>,
+    /// Query to match against, used together with --print
+    #[arg(short, long, default_value = "")]
+    query: String,
+    /// Print the ranked matches for the query to stdout without opening a window
+    #[arg(long)]
+    print: bool,
+    /// Print the matches as JSON
+    #[arg(long, requires = "print")]
+    json: bool,
+    /// Serve the clipboard text read from stdin, used to keep copied text after exiting
+    #[arg(long = clipboard::SERVE_FLAG, hide = true)]
+    serve_clipboard: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Manage the history of recently used items
+    History {
+        #[command(subcommand)]
+        command: HistoryCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum HistoryCommand {
+    /// List the items in the history, optionally of a single mode
+    List { mode: Option<String> },
+    /// Remove the items with the given text from the history of a mode
+    Forget { mode: String, text: String },
+    /// Clear the history of a mode, or of all modes
+    Clear { mode: Option<String> },
+    /// Remove the items of which the file or program no longer exists, pinned items are kept
+    Prune { mode: Option<String> },
+    /// Pin the items with the given text, so they are always shown first
+    Pin {
+        mode: String,
+        text: String,
+        /// Unpin the items instead
+        #[arg(long)]
+        unpin: bool,
+    },
+}
+
+fn run_history(command: HistoryCommand) -> anyhow::Result<()> {
+    let mut recent = RecentItems::load_or_default()?;
+    match command {
+        HistoryCommand::List { mode } => {
+            for (key, recent_item) in recent.iter() {
+                if mode.as_ref().is_none_or(|mode| mode == key) {
+                    println!(
+                        "{key}\t{}\t{}\t{}",
+                        recent_item.count(),
+                        if recent_item.pinned() { "pinned" } else { "" },
+                        recent_item.item.text
+                    );
+                }
+            }
+        }
+        HistoryCommand::Forget { mode, text } => {
+            let mut found = false;
+            for item in recent.get_items(&mode) {
+                if item.text == text {
+                    found |= recent.forget_and_save(&mode, &item)?;
+                }
+            }
+            if !found {
+                bail!("no item '{text}' in the history of mode '{mode}'");
+            }
+        }
+        HistoryCommand::Clear { mode } => recent.clear_and_save(mode.as_deref())?,
+        HistoryCommand::Prune { mode } => {
+            let pruned = recent.prune_and_save(mode.as_deref())?;
+            println!("removed {pruned} items");
+        }
+        HistoryCommand::Pin { mode, text, unpin } => {
+            let items: Vec<_> = recent
+                .get_items(&mode)
+                .into_iter()
+                .filter(|item| item.text == text)
+                .collect();
+            if items.is_empty() {
+                bail!("no item '{text}' in the history of mode '{mode}'");
+            }
+            for item in items {
+                recent.set_pinned_and_save(&mode, item, !unpin)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The modes that are searched by the combi mode
+const COMBI_MODES: [&str; 3] = ["apps", "run", "files"];
+const BUILTIN_MODES: [&str; 6] = ["apps", "run", "files", "combi", "calc", "emoji"];
+
+/// Loads a built-in mode or one of the `[[mode]]` or `[[plugin]]` tables of the config
+fn load_mode(name: &str, config: &Config) -> Option<Box<dyn Mode>> {
+    let mode: Box<dyn Mode> = match name {
+        "apps" => Box::new(AppsMode::load()),
+        "run" => Box::new(RunMode::load()),
+        "files" => Box::new(FilesMode::new(dirs::home_dir().unwrap())),
+        "calc" => Box::new(CalcMode::new()),
+        "emoji" => Box::new(EmojiMode::new(config.emoji.clone())),
+        "combi" => Box::new(CombiMode::new(
+            COMBI_MODES
+                .iter()
+                .map(|name| (name.to_string(), load_mode(name, config).unwrap()))
+                .collect(),
+        )),
+        _ => {
+            if let Some(mode) = config.modes.iter().find(|mode| mode.name == name) {
+                Box::new(CustomMode::new(mode.clone()))
+            } else {
+                let plugin = config.plugins.iter().find(|plugin| plugin.name == name)?;
+                Box::new(PluginMode::new(plugin.clone()))
+            }
+        }
+    };
+    Some(mode)
 }
 
 fn main() {
@@ -27,28 +159,86 @@ fn main() {
         .init();
 
     let args: Args = Args::parse();
-    let mode: Box<dyn Mode> = if args.dmenu {
+    if args.serve_clipboard {
+        if let Err(e) = clipboard::serve_text() {
+            eprintln!("Failed to serve the clipboard: {e:?}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(Command::History { command }) = args.command {
+        if let Err(e) = run_history(command) {
+            eprintln!("Failed to manage history: {e:?}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Failed to load config: {e:?}");
+            std::process::exit(1);
+        }
+    };
+    let plugin_names = config.plugins.iter().map(|plugin| &plugin.name);
+    for name in config
+        .modes
+        .iter()
+        .map(|mode| &mode.name)
+        .chain(plugin_names)
+    {
+        if BUILTIN_MODES.contains(&name.as_str()) {
+            log::warn!("mode '{name}' of the config is hidden by the built-in mode");
+        }
+    }
+    let (mut modes, active) = if args.dmenu {
         let mut buffer = String::new();
         stdin()
             .read_to_string(&mut buffer)
             .expect("Failed to read from stdin");
-        Box::new(DmenuMode::new(args.prompt, buffer))
+        let mode: Box<dyn Mode> = Box::new(DmenuMode::new(args.prompt, buffer));
+        (vec![mode], 0)
     } else {
-        match args.mode.as_str() {
-            "apps" => Box::new(AppsMode::load()),
-            "run" => Box::new(RunMode::load()),
-            "files" => Box::new(FilesMode::new(dirs::home_dir().unwrap())),
-            other => {
-                eprintln!("Unknown mode: {}", other);
-                std::process::exit(1);
+        let mut modes = Vec::new();
+        for name in &args.mode {
+            match load_mode(name, &config) {
+                Some(mode) => modes.push(mode),
+                None => {
+                    eprintln!("Unknown mode: {}", name);
+                    std::process::exit(1);
+                }
             }
         }
+        let active = match &args.show {
+            Some(show) => match args.mode.iter().position(|name| name == show) {
+                Some(active) => active,
+                None => {
+                    eprintln!("Mode to show is not one of the modes: {}", show);
+                    std::process::exit(1);
+                }
+            },
+            None => 0,
+        };
+        (modes, active)
     };
-    match Launcher::load(mode) {
-        Ok(launcher) => WinitApp::new(launcher).run(),
-        Err(e) => {
-            eprintln!("Failed to load launcher: {e:?}");
+    if args.print {
+        let mode = modes.swap_remove(active);
+        if let Err(e) = headless::print_matches(mode, &config, &args.query, args.json) {
+            eprintln!("Failed to print matches: {e:?}");
             std::process::exit(1);
         }
+        return;
     }
+    let recent = match RecentItems::load_or_default() {
+        Ok(recent) => recent,
+        Err(e) => {
+            eprintln!("Failed to load recent items: {e:?}");
+            std::process::exit(1);
+        }
+    };
+    let clipboard = SystemClipboard::new()
+        .inspect_err(|e| log::error!("Failed to access the clipboard: {e}"))
+        .ok()
+        .map(|clipboard| Box::new(clipboard) as Box<dyn Clipboard>);
+    WinitApp::new(Launcher::load(config, modes, active, recent, clipboard)).run();
 }
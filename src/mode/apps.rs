@@ -1,4 +1,4 @@
-use super::Mode;
+use super::{Engine, Matching, Mode, ScoredItem, Status};
 use crate::item::Action;
 use crate::winit_app::EventHandle;
 use crate::{file_finder, item::Item};
@@ -7,19 +7,16 @@ use rayon::prelude::*;
 use std::collections::HashSet;
 use std::io::BufRead;
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
 use std::{env, thread};
 use std::{ffi::OsStr, fs::File, io::BufReader, path::Path, time::Instant};
 
 pub struct AppsMode {
-    options: Arc<Mutex<Vec<Item>>>,
+    engine: Option<Engine>,
 }
 
 impl AppsMode {
     pub fn load() -> Self {
-        Self {
-            options: Arc::new(Mutex::new(Vec::new())),
-        }
+        Self { engine: None }
     }
 }
 
@@ -29,23 +26,33 @@ impl Mode for AppsMode {
     }
 
     fn run(&mut self, event_handle: EventHandle) {
-        let options = self.options.clone();
+        let mut engine = Engine::new(event_handle);
+        let mut injector = engine.injector();
+        self.engine = Some(engine);
+
         thread::spawn(move || {
-            let items = load_desktop_files();
-            event_handle.send_update();
-            let mut options_mut = options.lock().unwrap();
-            *options_mut = items;
+            injector.extend(load_desktop_files());
         });
     }
 
-    fn update(&mut self, input: &str) -> Vec<Item> {
-        let items = self.options.lock().unwrap().clone();
-        super::fuzzy_match(input, &items)
+    fn update(&mut self, input: &str, matching: Matching, limit: usize) -> Vec<ScoredItem> {
+        self.engine
+            .as_mut()
+            .map(|engine| engine.update(input, matching, limit))
+            .unwrap_or_default()
     }
 
-    fn cache_key(&self) -> Option<&'static str> {
+    fn cache_key(&self) -> Option<&str> {
         Some("apps")
     }
+
+    fn inline_calc(&self) -> bool {
+        true
+    }
+
+    fn status(&self) -> Status {
+        self.engine.as_ref().map(Engine::status).unwrap_or_default()
+    }
 }
 
 pub fn load_desktop_files() -> Vec<Item> {
@@ -288,11 +295,11 @@ fn unquote_args(input: &str) -> Vec<String> {
                 }
             }
             '\\' if in_quote => {
-                if let Some(next) = iter.peek() {
-                    if let '"' | '`' | '$' | '\\' = next {
-                        current.push(*next);
-                        iter.next();
-                    }
+                if let Some(next) = iter.peek()
+                    && let '"' | '`' | '$' | '\\' = next
+                {
+                    current.push(*next);
+                    iter.next();
                 }
             }
             '"' if !in_quote => in_quote = true,
@@ -0,0 +1,63 @@
+use super::{Matching, Mode, ScoredItem, Status};
+use crate::{
+    calc,
+    item::{Action, Item},
+    winit_app::EventHandle,
+};
+use std::sync::Arc;
+
+/// Shows the result of the input as a calculation, accepting it copies the result
+#[derive(Default)]
+pub struct CalcMode {
+    result: Option<Arc<Item>>,
+}
+
+impl CalcMode {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// The result of the input as an item, `None` if the input is not an expression
+fn calc_item(input: &str) -> Option<Item> {
+    let answer = calc::evaluate(input).ok()?;
+    Some(Item::new(
+        answer.text.clone(),
+        Action::Copy { text: answer.text },
+    ))
+}
+
+impl Mode for CalcMode {
+    fn display_name(&self) -> &str {
+        "Calc"
+    }
+
+    fn run(&mut self, _event_handle: EventHandle) {}
+
+    fn update(&mut self, input: &str, _matching: Matching, limit: usize) -> Vec<ScoredItem> {
+        self.result = calc_item(input).map(Arc::new);
+        self.result
+            .iter()
+            .take(limit)
+            .map(|item| ScoredItem {
+                item: item.clone(),
+                score: 0,
+                indices: Vec::new(),
+                source: None,
+            })
+            .collect()
+    }
+
+    fn cache_key(&self) -> Option<&str> {
+        None
+    }
+
+    fn status(&self) -> Status {
+        let count = self.result.is_some() as u32;
+        Status {
+            matched: count,
+            total: count,
+            loading: false,
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use super::{Matching, Mode, ScoredItem, Status};
+use crate::{item::Item, winit_app::EventHandle};
+use std::{cmp::Reverse, sync::Arc};
+
+/// A mode that is part of a [`CombiMode`]
+struct Source {
+    name: Arc<str>,
+    mode: Box<dyn Mode>,
+}
+
+/// Searches the items of several modes at once, every match is tagged with the name of its mode
+/// The input can be narrowed to a single mode with a prefix of its name, like `!apps firefox` or
+/// `:f notes`
+pub struct CombiMode {
+    sources: Vec<Source>,
+    /// The mode that the input of the last update was narrowed to
+    narrowed: Option<usize>,
+}
+
+impl CombiMode {
+    /// The modes are given together with the names that are used to tag and narrow to them
+    pub fn new(modes: Vec<(String, Box<dyn Mode>)>) -> Self {
+        Self {
+            sources: modes
+                .into_iter()
+                .map(|(name, mode)| Source {
+                    name: name.into(),
+                    mode,
+                })
+                .collect(),
+            narrowed: None,
+        }
+    }
+
+    /// Splits a prefix like `!apps ` off the input, returns the index of the mode it narrows to
+    /// and the remaining input
+    /// The input is already narrowed while the prefix is typed, so the results of the mode are
+    /// shown right away
+    fn narrow<'a>(&self, input: &'a str) -> (Option<usize>, &'a str) {
+        let Some(rest) = input.strip_prefix(['!', ':']) else {
+            return (None, input);
+        };
+        let (prefix, query) = rest.split_once(' ').unwrap_or((rest, ""));
+        let prefix = prefix.to_lowercase();
+        if prefix.is_empty() {
+            return (None, input);
+        }
+        match self
+            .sources
+            .iter()
+            .position(|source| source.name.to_lowercase().starts_with(&prefix))
+        {
+            Some(i) => (Some(i), query),
+            None => (None, input),
+        }
+    }
+}
+
+impl Mode for CombiMode {
+    fn display_name(&self) -> &str {
+        "Combi"
+    }
+
+    fn run(&mut self, event_handle: EventHandle) {
+        for source in &mut self.sources {
+            source.mode.run(event_handle.clone());
+        }
+    }
+
+    fn update(&mut self, input: &str, matching: Matching, limit: usize) -> Vec<ScoredItem> {
+        let (narrowed, query) = self.narrow(input);
+        self.narrowed = narrowed;
+        let mut matches = Vec::new();
+        for (i, source) in self.sources.iter_mut().enumerate() {
+            if narrowed.is_some_and(|narrowed| narrowed != i) {
+                continue;
+            }
+            matches.extend(
+                source
+                    .mode
+                    .update(query, matching, limit)
+                    .into_iter()
+                    .map(|scored| ScoredItem {
+                        source: Some(source.name.clone()),
+                        ..scored
+                    }),
+            );
+        }
+        // the sort is stable, so equal scores keep the order of the modes
+        matches.sort_by_key(|scored| Reverse(scored.score));
+        matches.truncate(limit);
+        matches
+    }
+
+    fn cache_key(&self) -> Option<&str> {
+        Some("combi")
+    }
+
+    fn inline_calc(&self) -> bool {
+        true
+    }
+
+    /// The history does not know which mode its items came from, so it is not searched when
+    /// the input is narrowed to a single mode
+    fn history_query<'a>(&self, input: &'a str) -> Option<&'a str> {
+        match self.narrow(input) {
+            (Some(_), _) => None,
+            (None, input) => Some(input),
+        }
+    }
+
+    /// Only the mode that the input is narrowed to counts, the other modes are not updated
+    fn status(&self) -> Status {
+        self.sources
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| self.narrowed.is_none_or(|narrowed| narrowed == *i))
+            .map(|(_, source)| source.mode.status())
+            .fold(Status::default(), |status, source| Status {
+                matched: status.matched + source.matched,
+                total: status.total + source.total,
+                loading: status.loading || source.loading,
+            })
+    }
+
+    fn custom_item(&self, input: &str) -> Option<Item> {
+        match self.narrow(input) {
+            (Some(i), query) => self.sources[i].mode.custom_item(query),
+            (None, input) => self
+                .sources
+                .iter()
+                .find_map(|source| source.mode.custom_item(input)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mode::DmenuMode;
+
+    fn combi() -> CombiMode {
+        let source = |name: &str| -> (String, Box<dyn Mode>) {
+            (
+                name.to_string(),
+                Box::new(DmenuMode::new(Some(name.to_string()), String::new())),
+            )
+        };
+        CombiMode::new(vec![source("apps"), source("files")])
+    }
+
+    #[test]
+    fn test_narrow() {
+        let combi = combi();
+        assert_eq!(combi.narrow("!apps fire"), (Some(0), "fire"));
+        assert_eq!(combi.narrow(":f notes"), (Some(1), "notes"));
+        // narrowed while the prefix is typed
+        assert_eq!(combi.narrow("!ap"), (Some(0), ""));
+        assert_eq!(combi.narrow("!music x"), (None, "!music x"));
+        assert_eq!(combi.narrow("fire"), (None, "fire"));
+        assert_eq!(combi.history_query(":f notes"), None);
+    }
+}
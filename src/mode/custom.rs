@@ -0,0 +1,179 @@
+use super::{Engine, MatchKind, Matching, Mode, ScoredItem, Status};
+use crate::{
+    item::{Action, Item, shell_quote},
+    winit_app::EventHandle,
+};
+use serde::Deserialize;
+use std::{
+    io::{BufRead, BufReader},
+    process::{Command, Stdio},
+    thread,
+};
+
+/// How the output of the command of a custom mode is read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ItemFormat {
+    /// Every line is an item
+    #[default]
+    Lines,
+    /// Every line is a JSON object like `{"text": "Firefox", "value": "firefox"}`, the value is
+    /// optional and defaults to the text
+    Json,
+}
+
+/// A `[[mode]]` table of the config
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomModeConfig {
+    /// Used to select the mode with `-m`
+    pub name: String,
+    /// Shell command that prints the items to stdout
+    pub command: String,
+    #[serde(default)]
+    pub format: ItemFormat,
+    /// Shell command that is run with the chosen item, `{}` is replaced by the quoted value of the
+    /// item, the text of the item is printed to stdout if there is no action
+    pub action: Option<String>,
+    /// Key of the history of the mode, no history is kept without a key
+    pub cache_key: Option<String>,
+    /// Overrides the kind of the `[matching]` section for this mode
+    pub matching: Option<MatchKind>,
+}
+
+#[derive(Deserialize)]
+struct JsonItem {
+    text: String,
+    value: Option<String>,
+}
+
+/// A mode defined in the config, its items are produced by a shell command
+pub struct CustomMode {
+    config: CustomModeConfig,
+    engine: Option<Engine>,
+}
+
+impl CustomMode {
+    pub fn new(config: CustomModeConfig) -> Self {
+        Self {
+            config,
+            engine: None,
+        }
+    }
+}
+
+impl CustomModeConfig {
+    /// Parses a line of the output of the command, `None` if the line is not a valid item
+    fn parse_item(&self, line: &str) -> Option<Item> {
+        let (text, value) = match self.format {
+            ItemFormat::Lines => (line.to_string(), None),
+            ItemFormat::Json => match serde_json::from_str::<JsonItem>(line) {
+                Ok(item) => (item.text, item.value),
+                Err(e) => {
+                    log::warn!("mode '{}': invalid item '{line}': {e}", self.name);
+                    return None;
+                }
+            },
+        };
+        if text.is_empty() {
+            return None;
+        }
+        Some(match &self.action {
+            Some(action) => {
+                let command = action.replace("{}", &shell_quote(value.as_ref().unwrap_or(&text)));
+                Item::new(text, Action::Shell { command })
+            }
+            None => Item::new_selection(text),
+        })
+    }
+}
+
+impl Mode for CustomMode {
+    fn display_name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn run(&mut self, event_handle: EventHandle) {
+        let mut engine = Engine::new(event_handle);
+        let mut injector = engine.injector();
+        self.engine = Some(engine);
+
+        let config = self.config.clone();
+        thread::spawn(move || {
+            let mut child = match Command::new("sh")
+                .args(["-c", &config.command])
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .spawn()
+            {
+                Ok(child) => child,
+                Err(e) => {
+                    log::error!("mode '{}': failed to run command: {e}", config.name);
+                    return;
+                }
+            };
+            let stdout = BufReader::new(child.stdout.take().unwrap());
+            injector.extend(
+                stdout
+                    .lines()
+                    .map_while(Result::ok)
+                    .filter_map(|line| config.parse_item(&line)),
+            );
+            match child.wait() {
+                Ok(status) if !status.success() => {
+                    log::warn!("mode '{}': command exited with {status}", config.name)
+                }
+                Err(e) => log::error!("mode '{}': failed to wait for command: {e}", config.name),
+                Ok(_) => {}
+            }
+        });
+    }
+
+    fn update(&mut self, input: &str, matching: Matching, limit: usize) -> Vec<ScoredItem> {
+        self.engine
+            .as_mut()
+            .map(|engine| engine.update(input, matching, limit))
+            .unwrap_or_default()
+    }
+
+    fn cache_key(&self) -> Option<&str> {
+        self.config.cache_key.as_deref()
+    }
+
+    fn match_kind(&self) -> Option<MatchKind> {
+        self.config.matching
+    }
+
+    fn status(&self) -> Status {
+        self.engine.as_ref().map(Engine::status).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_items() {
+        let config: CustomModeConfig = toml::from_str(
+            r#"
+            name = "ssh"
+            command = "cat hosts"
+            format = "json"
+            action = "alacritty -e ssh {}"
+            "#,
+        )
+        .unwrap();
+        let item = config
+            .parse_item(r#"{"text": "Home server", "value": "o'neil@home"}"#)
+            .unwrap();
+        assert_eq!(item.text, "Home server");
+        assert_eq!(
+            item.action,
+            Action::Shell {
+                command: r"alacritty -e ssh 'o'\''neil@home'".to_string()
+            }
+        );
+        assert!(config.parse_item("not json").is_none());
+    }
+}
@@ -1,30 +1,55 @@
-use super::SimpleMode;
-use crate::item::Item;
+use super::{Engine, Matching, Mode, ScoredItem, Status};
+use crate::{item::Item, winit_app::EventHandle};
+use std::thread;
 
 pub struct DmenuMode {
     prompt: String,
-    options: Vec<Item>,
+    input: String,
+    engine: Option<Engine>,
 }
 
 impl DmenuMode {
     pub fn new(prompt: Option<String>, input: String) -> Self {
-        let options = input
-            .lines()
-            .map(|s| Item::new_selection(s.to_string()))
-            .collect();
         Self {
             prompt: prompt.unwrap_or("dmenu".to_string()),
-            options,
+            input,
+            engine: None,
         }
     }
 }
 
-impl SimpleMode for DmenuMode {
+impl Mode for DmenuMode {
     fn display_name(&self) -> &str {
         &self.prompt
     }
 
-    fn get_items(&mut self) -> &Vec<Item> {
-        &self.options
+    fn run(&mut self, event_handle: EventHandle) {
+        let mut engine = Engine::new(event_handle);
+        let mut injector = engine.injector();
+        self.engine = Some(engine);
+
+        let input = std::mem::take(&mut self.input);
+        thread::spawn(move || {
+            injector.extend(input.lines().map(|s| Item::new_selection(s.to_string())));
+        });
+    }
+
+    fn update(&mut self, input: &str, matching: Matching, limit: usize) -> Vec<ScoredItem> {
+        self.engine
+            .as_mut()
+            .map(|engine| engine.update(input, matching, limit))
+            .unwrap_or_default()
+    }
+
+    fn cache_key(&self) -> Option<&str> {
+        None
+    }
+
+    fn custom_item(&self, input: &str) -> Option<Item> {
+        Some(Item::new_selection(input.to_string()))
+    }
+
+    fn status(&self) -> Status {
+        self.engine.as_ref().map(Engine::status).unwrap_or_default()
     }
 }
@@ -0,0 +1,170 @@
+use super::{Engine, Matching, Mode, ScoredItem, Status};
+use crate::{
+    item::{Action, Item, shell_quote},
+    winit_app::EventHandle,
+};
+use serde::Deserialize;
+use std::thread;
+
+/// A character of the table that `build.rs` generates from the Unicode data files in `data/`
+struct Character {
+    text: &'static str,
+    name: &'static str,
+    /// The emoji group or the kind of character, like `food & drink` or `currency symbol`
+    group: &'static str,
+    /// Other words the emoji is known by, from the CLDR annotations
+    keywords: &'static [&'static str],
+    /// The variants of an emoji with a skin tone, in the order of [`SkinTone`]
+    skin_tones: Option<[&'static str; 5]>,
+}
+
+include!(concat!(env!("OUT_DIR"), "/characters.rs"));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SkinTone {
+    Light,
+    MediumLight,
+    Medium,
+    MediumDark,
+    Dark,
+}
+
+/// The `[emoji]` section of the config
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EmojiConfig {
+    /// Skin tone of the emoji that have variants, they are yellow without a skin tone
+    pub skin_tone: Option<SkinTone>,
+    /// Shell command that types the chosen character instead of copying it, like `wtype`, the
+    /// quoted character is appended to it
+    pub type_command: Option<String>,
+}
+
+impl EmojiConfig {
+    fn item(&self, character: &Character) -> Item {
+        let text = match (self.skin_tone, character.skin_tones) {
+            (Some(tone), Some(variants)) => variants[tone as usize],
+            _ => character.text,
+        };
+        let action = match &self.type_command {
+            Some(command) => Action::Shell {
+                command: format!("{command} {}", shell_quote(text)),
+            },
+            None => Action::Copy {
+                text: text.to_string(),
+            },
+        };
+        // the group and the keywords are part of the text, so they can be searched
+        let mut item_text = format!("{text}  {} · {}", character.name, character.group);
+        if !character.keywords.is_empty() {
+            item_text.push_str(" · ");
+            item_text.push_str(&character.keywords.join(", "));
+        }
+        Item::new(item_text, action)
+    }
+}
+
+/// Searches emoji and other Unicode characters by their name, group and keywords
+pub struct EmojiMode {
+    config: EmojiConfig,
+    engine: Option<Engine>,
+}
+
+impl EmojiMode {
+    pub fn new(config: EmojiConfig) -> Self {
+        Self {
+            config,
+            engine: None,
+        }
+    }
+}
+
+impl Mode for EmojiMode {
+    fn display_name(&self) -> &str {
+        "Emoji"
+    }
+
+    fn run(&mut self, event_handle: EventHandle) {
+        let mut engine = Engine::new(event_handle);
+        let mut injector = engine.injector();
+        self.engine = Some(engine);
+
+        let config = self.config.clone();
+        thread::spawn(move || {
+            injector.extend(CHARACTERS.iter().map(|character| config.item(character)));
+        });
+    }
+
+    fn update(&mut self, input: &str, matching: Matching, limit: usize) -> Vec<ScoredItem> {
+        self.engine
+            .as_mut()
+            .map(|engine| engine.update(input, matching, limit))
+            .unwrap_or_default()
+    }
+
+    fn cache_key(&self) -> Option<&str> {
+        Some("emoji")
+    }
+
+    fn status(&self) -> Status {
+        self.engine.as_ref().map(Engine::status).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nucleo::{Config, Matcher};
+
+    fn find(name: &str) -> &'static Character {
+        CHARACTERS.iter().find(|c| c.name == name).unwrap()
+    }
+
+    #[test]
+    fn test_character_table() {
+        assert_eq!(find("grinning face").text, "😀");
+        assert_eq!(find("grinning face").group, "smileys & emotion");
+        assert_eq!(find("euro sign").group, "currency symbol");
+        // the emoji name is used instead of the character name
+        assert!(CHARACTERS.iter().all(|c| c.name != "heavy black heart"));
+        let waving = find("waving hand");
+        assert_eq!(waving.skin_tones.unwrap()[SkinTone::Dark as usize], "👋🏿");
+        assert!(find("kiss: woman, man").skin_tones.is_some());
+        assert!(find("red heart").skin_tones.is_none());
+    }
+
+    #[test]
+    fn test_items() {
+        let waving = find("waving hand");
+        let item = EmojiConfig::default().item(waving);
+        assert_eq!(item.text, "👋  waving hand · people & body");
+        assert_eq!(item.copy_text(), "👋");
+        let config = EmojiConfig {
+            skin_tone: Some(SkinTone::MediumLight),
+            type_command: Some("wtype".to_string()),
+        };
+        assert_eq!(
+            config.item(waving).action,
+            Action::Shell {
+                command: "wtype '👋🏼'".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_keywords_are_searched() {
+        let grinning = Character {
+            keywords: &["happy", "smile"],
+            ..*find("grinning face")
+        };
+        let item = EmojiConfig::default().item(&grinning);
+        assert_eq!(
+            item.text,
+            "😀  grinning face · smileys & emotion · happy, smile"
+        );
+        let mut matcher = Matcher::new(Config::DEFAULT);
+        let query = Matching::default().query("happy");
+        assert!(query.match_text(&item.text, &mut matcher).is_some());
+    }
+}
@@ -0,0 +1,241 @@
+use super::{MatchKind, Matching, ScoredItem, Status};
+use crate::{item::Item, winit_app::EventHandle};
+use nucleo::{Config, Matcher, Nucleo, pattern::Normalization};
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Minimum time between two update events sent by the matcher threads
+pub(super) const UPDATE_INTERVAL: Duration = Duration::from_millis(10);
+/// Maximum time the UI thread waits for the matcher threads on each update
+const TICK_TIMEOUT_MS: u64 = 10;
+
+/// Matches the items of a mode incrementally on background threads
+/// Items can be injected from any thread while the input is being matched, every update only
+/// waits briefly for the matcher threads, so large numbers of items never block the UI
+pub struct Engine {
+    nucleo: Nucleo<Arc<Item>>,
+    /// Used to score the matched items and find the matched characters, the snapshot only
+    /// contains their order
+    matcher: Matcher,
+    current_input: String,
+    current_matching: Matching,
+    /// Whether the last tick may not have matched all items, because the matcher threads were
+    /// still running or items could still be injected, also set when the pattern changes or an
+    /// injector is created until the next tick
+    running: bool,
+    /// All matches of a word boundary or regex input, nucleo only prefilters them
+    filtered: Option<Vec<ScoredItem>>,
+    /// Computes the filtered matches of the current snapshot
+    filter_job: Option<FilterJob>,
+    event_handle: EventHandle,
+}
+
+impl Engine {
+    pub fn new(event_handle: EventHandle) -> Self {
+        let last_update = Mutex::new(Instant::now() - UPDATE_INTERVAL);
+        let notify_handle = event_handle.clone();
+        let notify = Arc::new(move || {
+            let mut last_update = last_update.lock().unwrap();
+            if last_update.elapsed() > UPDATE_INTERVAL {
+                *last_update = Instant::now();
+                notify_handle.send_update();
+            }
+        });
+        Self {
+            nucleo: Nucleo::new(Config::DEFAULT.match_paths(), notify, None, 1),
+            matcher: Matcher::new(Config::DEFAULT.match_paths()),
+            current_input: String::new(),
+            current_matching: Matching::default(),
+            running: false,
+            filtered: None,
+            filter_job: None,
+            event_handle,
+        }
+    }
+
+    /// Returns a handle to add items from another thread, the engine keeps loading until all
+    /// injectors are dropped
+    pub fn injector(&mut self) -> Injector {
+        self.running = true;
+        Injector {
+            inner: Some(self.nucleo.injector()),
+            event_handle: self.event_handle.clone(),
+        }
+    }
+
+    pub fn update(&mut self, input: &str, matching: Matching, limit: usize) -> Vec<ScoredItem> {
+        if input != self.current_input || matching != self.current_matching {
+            let pattern = matching.nucleo_pattern(input);
+            // when the pattern only grows, nucleo can search the previous matches only
+            let append = matching == self.current_matching
+                && pattern.starts_with(&self.current_matching.nucleo_pattern(&self.current_input));
+            self.nucleo.pattern.reparse(
+                0,
+                &pattern,
+                matching.case_matching(),
+                Normalization::Smart,
+                append,
+            );
+            self.current_input = input.to_string();
+            self.current_matching = matching;
+            self.running = true;
+            self.filtered = None;
+            self.filter_job = None;
+        }
+        // items injected after the injectors are counted are seen by the next tick only
+        let injecting = self.nucleo.active_injectors() > 0;
+        let tick = self.nucleo.tick(TICK_TIMEOUT_MS);
+        self.running = tick.running || injecting;
+        if tick.changed {
+            self.filtered = None;
+            self.filter_job = None;
+        }
+        let snapshot = self.nucleo.snapshot();
+        let query = matching.query(input);
+        let matcher = &mut self.matcher;
+        let matches = snapshot.matched_items(..).filter_map(|item| {
+            let (score, indices) = query.match_text(&item.data.text, matcher)?;
+            Some(ScoredItem {
+                item: item.data.clone(),
+                score,
+                indices,
+                source: None,
+            })
+        });
+        if !matching.kind.is_prefiltered() {
+            return matches.take(limit).collect();
+        }
+        if self.filtered.is_none() && self.filter_job.is_none() {
+            let items = snapshot.matched_items(..).map(|i| i.data.clone()).collect();
+            self.filter_job = Some(FilterJob::spawn(
+                items,
+                input.to_string(),
+                matching,
+                self.event_handle.clone(),
+            ));
+        }
+        if let Some(job) = &self.filter_job
+            && let Ok(filtered) = job.receiver.try_recv()
+        {
+            self.filtered = Some(filtered);
+            self.filter_job = None;
+        }
+        match &self.filtered {
+            Some(filtered) => filtered.iter().take(limit).cloned().collect(),
+            // until the filter job has finished, the first matches are shown in the order of
+            // nucleo
+            None => matches.take(limit).collect(),
+        }
+    }
+
+    /// Until word boundary or regex filtering has finished, the number of matched items is
+    /// counted before filtering, so it can be higher than the number of matches
+    pub fn status(&self) -> Status {
+        let snapshot = self.nucleo.snapshot();
+        let matched = match &self.filtered {
+            Some(filtered) => filtered.len() as u32,
+            None => snapshot.matched_item_count(),
+        };
+        Status {
+            matched,
+            total: snapshot.item_count(),
+            loading: self.running || self.filter_job.is_some(),
+        }
+    }
+}
+
+/// Filters the matches of a word boundary or regex input on another thread, so checking every
+/// item never blocks the UI
+struct FilterJob {
+    receiver: Receiver<Vec<ScoredItem>>,
+    canceled: Arc<AtomicBool>,
+}
+
+impl FilterJob {
+    fn spawn(
+        items: Vec<Arc<Item>>,
+        input: String,
+        matching: Matching,
+        event_handle: EventHandle,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let canceled = Arc::new(AtomicBool::new(false));
+        let job_canceled = canceled.clone();
+        thread::spawn(move || {
+            let query = matching.query(&input);
+            let mut matcher = Matcher::new(Config::DEFAULT.match_paths());
+            let mut matches = Vec::new();
+            for item in items {
+                if job_canceled.load(Ordering::Relaxed) {
+                    return;
+                }
+                if let Some((score, indices)) = query.match_text(&item.text, &mut matcher) {
+                    matches.push(ScoredItem {
+                        item,
+                        score,
+                        indices,
+                        source: None,
+                    });
+                }
+            }
+            if matching.kind == MatchKind::Regex {
+                // nucleo does not see the regex, so its matches are not sorted yet
+                matches.sort_by_key(|m| std::cmp::Reverse(m.score));
+            }
+            if sender.send(matches).is_ok() {
+                event_handle.send_update();
+            }
+        });
+        Self { receiver, canceled }
+    }
+}
+
+impl Drop for FilterJob {
+    /// Stops the thread, its result is no longer needed
+    fn drop(&mut self) {
+        self.canceled.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Adds items to an [`Engine`]
+#[derive(Clone)]
+pub struct Injector {
+    /// Only `None` while the injector is being dropped
+    inner: Option<nucleo::Injector<Arc<Item>>>,
+    event_handle: EventHandle,
+}
+
+impl Injector {
+    pub fn push(&self, item: Item) {
+        if let Some(inner) = &self.inner {
+            inner.push(Arc::new(item), |item, columns| {
+                columns[0] = item.text.as_str().into();
+            });
+        }
+    }
+}
+
+impl Extend<Item> for Injector {
+    fn extend<T: IntoIterator<Item = Item>>(&mut self, iter: T) {
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
+
+impl Drop for Injector {
+    /// Notifies that the injection may have finished, this update is never throttled
+    fn drop(&mut self) {
+        // the nucleo injector must be dropped first, so the engine is no longer loading when the
+        // update is handled
+        drop(self.inner.take());
+        self.event_handle.send_update();
+    }
+}
@@ -1,31 +1,18 @@
-use super::Mode;
+use super::{Engine, Matching, Mode, ScoredItem, Status};
 use crate::{
     file_finder::{self, FileResult},
-    item::Item,
     winit_app::EventHandle,
 };
-use nucleo::{Config, Nucleo};
-use std::{
-    cell::RefCell,
-    path::PathBuf,
-    sync::{Arc, mpsc},
-    thread,
-    time::Instant,
-};
+use std::{path::PathBuf, sync::mpsc, thread};
 
 pub struct FilesMode {
     root: PathBuf,
-    nucleo: Option<Nucleo<Item>>,
-    current_input: String,
+    engine: Option<Engine>,
 }
 
 impl FilesMode {
     pub fn new(root: PathBuf) -> Self {
-        Self {
-            root,
-            nucleo: None,
-            current_input: String::new(),
-        }
+        Self { root, engine: None }
     }
 }
 
@@ -35,70 +22,32 @@ impl Mode for FilesMode {
     }
 
     fn run(&mut self, event_handle: EventHandle) {
-        let config = Config::DEFAULT.match_paths();
-
-        let event_handle = Arc::new(event_handle);
-        thread_local! {
-            static LAST_UPDATE: RefCell<Instant> = RefCell::new(Instant::now() - std::time::Duration::from_secs(1));
-        }
-        let mut nucleo = Nucleo::new(
-            config,
-            Arc::new(move || {
-                LAST_UPDATE.with_borrow_mut(|last_update| {
-                    if last_update.elapsed().as_millis() > 10 {
-                        *last_update = Instant::now();
-                        event_handle.send_update();
-                    }
-                });
-            }),
-            None,
-            1,
-        );
-        nucleo.pattern.reparse(
-            0,
-            "",
-            nucleo::pattern::CaseMatching::Ignore,
-            nucleo::pattern::Normalization::Smart,
-            false,
-        );
-        nucleo.tick(10);
-        let injector = nucleo.injector();
-        self.nucleo = Some(nucleo);
+        let mut engine = Engine::new(event_handle);
+        let injector = engine.injector();
+        self.engine = Some(engine);
 
         let root = self.root.clone();
         thread::spawn(move || {
             let (files_tx, files_rx) = mpsc::channel::<FileResult>();
             file_finder::find_all_files(&root, files_tx);
             while let Ok(entry) = files_rx.recv() {
-                injector.push(entry.into(), |item, b| {
-                    b[0] = item.as_ref().into();
-                });
+                injector.push(entry.into());
             }
         });
     }
 
-    fn update(&mut self, input: &str) -> Vec<Item> {
-        let nucleo = self.nucleo.as_mut().unwrap();
-        if input != self.current_input {
-            nucleo.pattern.reparse(
-                0,
-                input,
-                nucleo::pattern::CaseMatching::Ignore,
-                nucleo::pattern::Normalization::Smart,
-                self.current_input.starts_with(input),
-            );
-            self.current_input = input.to_string();
-        }
-        nucleo.tick(10);
-        let snapshot = nucleo.snapshot();
-        let matches = snapshot
-            .matched_items(..snapshot.matched_item_count().min(64))
-            .map(|item| item.data.clone())
-            .collect();
-        matches
+    fn update(&mut self, input: &str, matching: Matching, limit: usize) -> Vec<ScoredItem> {
+        self.engine
+            .as_mut()
+            .map(|engine| engine.update(input, matching, limit))
+            .unwrap_or_default()
     }
 
-    fn cache_key(&self) -> Option<&'static str> {
+    fn cache_key(&self) -> Option<&str> {
         Some("files")
     }
+
+    fn status(&self) -> Status {
+        self.engine.as_ref().map(Engine::status).unwrap_or_default()
+    }
 }
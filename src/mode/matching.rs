@@ -0,0 +1,331 @@
+use super::ScoredItem;
+use crate::item::Item;
+use nucleo::{
+    Config, Matcher, Utf32Str,
+    chars::graphemes,
+    pattern::{CaseMatching, Normalization, Pattern},
+};
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+use std::sync::Arc;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// How the input is matched against the items
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MatchKind {
+    /// The characters of each word must appear in order, supports the fzf syntax (`^foo`, `'foo`,
+    /// `foo$` and `!foo`)
+    #[default]
+    Fuzzy,
+    /// The text must start with the input
+    Prefix,
+    /// Each word must appear in the text
+    Substring,
+    /// The text must equal the input
+    Exact,
+    /// Each word must appear in the text at the start of a word
+    WordBoundary,
+    /// The input is a regular expression that must match somewhere in the text
+    Regex,
+}
+
+impl MatchKind {
+    const ALL: [MatchKind; 6] = [
+        MatchKind::Fuzzy,
+        MatchKind::Prefix,
+        MatchKind::Substring,
+        MatchKind::Exact,
+        MatchKind::WordBoundary,
+        MatchKind::Regex,
+    ];
+
+    /// The next kind, used to cycle through the kinds at runtime
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|kind| *kind == self).unwrap();
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    /// Whether nucleo only prefilters the items, so the matches must also be checked with
+    /// [`Query::match_text`]
+    pub fn is_prefiltered(self) -> bool {
+        matches!(self, MatchKind::WordBoundary | MatchKind::Regex)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MatchKind::Fuzzy => "fuzzy",
+            MatchKind::Prefix => "prefix",
+            MatchKind::Substring => "substring",
+            MatchKind::Exact => "exact",
+            MatchKind::WordBoundary => "word",
+            MatchKind::Regex => "regex",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CaseSensitivity {
+    /// Case sensitive only if the input contains an uppercase character
+    Smart,
+    #[default]
+    Ignore,
+    Respect,
+}
+
+/// The `[matching]` section of the config
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(default)]
+pub struct Matching {
+    pub kind: MatchKind,
+    pub case: CaseSensitivity,
+}
+
+impl Matching {
+    pub fn case_matching(&self) -> CaseMatching {
+        match self.case {
+            CaseSensitivity::Smart => CaseMatching::Smart,
+            CaseSensitivity::Ignore => CaseMatching::Ignore,
+            CaseSensitivity::Respect => CaseMatching::Respect,
+        }
+    }
+
+    /// Translates the input into the fzf syntax that is parsed by nucleo
+    /// For word boundary and regex matching this is only a prefilter, the items it matches must
+    /// also be checked with [`Query::match_text`]
+    pub fn nucleo_pattern(&self, input: &str) -> String {
+        match self.kind {
+            MatchKind::Fuzzy => input.to_string(),
+            MatchKind::Prefix => format!("^{}", escape_end(&escape_whitespace(input))),
+            // the trailing `$` is always the last one, so a `$` in the input needs no escaping
+            MatchKind::Exact => format!("^{}$", escape_whitespace(input)),
+            MatchKind::Substring | MatchKind::WordBoundary => input
+                .split_whitespace()
+                .map(|word| format!("'{}", escape_end(word)))
+                .collect::<Vec<_>>()
+                .join(" "),
+            MatchKind::Regex => String::new(),
+        }
+    }
+
+    pub fn query(&self, input: &str) -> Query {
+        let pattern = Pattern::parse(
+            &self.nucleo_pattern(input),
+            self.case_matching(),
+            Normalization::Smart,
+        );
+        let ignore_case = match self.case {
+            CaseSensitivity::Smart => !input.chars().any(char::is_uppercase),
+            CaseSensitivity::Ignore => true,
+            CaseSensitivity::Respect => false,
+        };
+        let filter = match self.kind {
+            MatchKind::WordBoundary => Filter::WordBoundary {
+                words: input
+                    .split_whitespace()
+                    .map(|word| graphemes(word).collect())
+                    .collect(),
+                ignore_case,
+            },
+            MatchKind::Regex if !input.is_empty() => {
+                match RegexBuilder::new(input)
+                    .case_insensitive(ignore_case)
+                    .build()
+                {
+                    Ok(regex) => Filter::Regex(Some(regex)),
+                    Err(e) => {
+                        log::debug!("invalid regex '{input}': {e}");
+                        Filter::Regex(None)
+                    }
+                }
+            }
+            _ => Filter::None,
+        };
+        Query { pattern, filter }
+    }
+}
+
+/// Escapes the whitespace so the input is parsed as a single atom
+fn escape_whitespace(input: &str) -> String {
+    input.replace(' ', "\\ ")
+}
+
+/// Escapes a trailing `$`, which would otherwise change the kind of the atom
+fn escape_end(word: &str) -> String {
+    match word.strip_suffix('$') {
+        Some(word) => format!("{word}\\$"),
+        None => word.to_string(),
+    }
+}
+
+enum Filter {
+    None,
+    WordBoundary {
+        words: Vec<Vec<char>>,
+        ignore_case: bool,
+    },
+    /// `None` if the input is not a valid regex, which matches nothing
+    Regex(Option<Regex>),
+}
+
+/// A parsed input, created by [`Matching::query`]
+pub struct Query {
+    pattern: Pattern,
+    filter: Filter,
+}
+
+impl Query {
+    /// Scores the text and finds the sorted indices of the matched characters, like nucleo the
+    /// indices count grapheme clusters
+    pub fn match_text(&self, text: &str, matcher: &mut Matcher) -> Option<(u32, Vec<u32>)> {
+        let mut buf = Vec::new();
+        let score = self.pattern.score(Utf32Str::new(text, &mut buf), matcher)?;
+        let mut indices = match &self.filter {
+            Filter::None => {
+                let mut indices = Vec::new();
+                self.pattern
+                    .indices(Utf32Str::new(text, &mut buf), matcher, &mut indices);
+                indices
+            }
+            Filter::WordBoundary { words, ignore_case } => {
+                word_boundary_indices(text, words, *ignore_case)?
+            }
+            Filter::Regex(regex) => {
+                let found = regex.as_ref()?.find(text)?;
+                let indices: Vec<u32> = text
+                    .grapheme_indices(true)
+                    .enumerate()
+                    .filter(|(_, (i, grapheme))| {
+                        *i < found.end() && i + grapheme.len() > found.start()
+                    })
+                    .map(|(index, _)| index as u32)
+                    .collect();
+                let start = indices.first().copied().unwrap_or_default();
+                // similar to the scale of the nucleo scores: longer and earlier matches score higher
+                let score = 16 * indices.len() as u32 + 32u32.saturating_sub(start);
+                return Some((score, indices));
+            }
+        };
+        indices.sort_unstable();
+        indices.dedup();
+        Some((score, indices))
+    }
+
+    pub fn match_list(&self, items: &[Arc<Item>]) -> Vec<ScoredItem> {
+        let mut matcher = Matcher::new(Config::DEFAULT.match_paths());
+        let mut matches: Vec<ScoredItem> = items
+            .iter()
+            .filter_map(|item| {
+                let (score, indices) = self.match_text(&item.text, &mut matcher)?;
+                Some(ScoredItem {
+                    item: item.clone(),
+                    score,
+                    indices,
+                    source: None,
+                })
+            })
+            .collect();
+        matches.sort_by_key(|m| std::cmp::Reverse(m.score));
+        matches
+    }
+}
+
+/// Finds each word at the start of a word in the text
+fn word_boundary_indices(text: &str, words: &[Vec<char>], ignore_case: bool) -> Option<Vec<u32>> {
+    let text: Vec<char> = graphemes(text).collect();
+    let eq = |a: char, b: char| {
+        if ignore_case {
+            a.to_lowercase().eq(b.to_lowercase())
+        } else {
+            a == b
+        }
+    };
+    let is_boundary = |i: usize| {
+        i == 0
+            || !text[i - 1].is_alphanumeric() && text[i].is_alphanumeric()
+            || text[i - 1].is_lowercase() && text[i].is_uppercase()
+    };
+    let mut indices = Vec::new();
+    for word in words {
+        let start = (0..text.len()).find(|&i| {
+            is_boundary(i)
+                && text.len() - i >= word.len()
+                && word.iter().zip(&text[i..]).all(|(a, b)| eq(*a, *b))
+        })?;
+        indices.extend(start as u32..(start + word.len()) as u32);
+    }
+    Some(indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(kind: MatchKind, case: CaseSensitivity, input: &str, text: &str) -> bool {
+        let mut matcher = Matcher::new(Config::DEFAULT);
+        Matching { kind, case }
+            .query(input)
+            .match_text(text, &mut matcher)
+            .is_some()
+    }
+
+    #[test]
+    fn test_match_kinds() {
+        use MatchKind::*;
+        let case = CaseSensitivity::Ignore;
+        assert!(matches(Fuzzy, case, "ffx", "Firefox"));
+        assert!(matches(Prefix, case, "fire", "Firefox"));
+        assert!(!matches(Prefix, case, "fox", "Firefox"));
+        assert!(matches(Prefix, case, "visual st", "Visual Studio"));
+        assert!(matches(Substring, case, "fox fire", "Firefox"));
+        assert!(!matches(Substring, case, "ffx", "Firefox"));
+        assert!(matches(Exact, case, "firefox", "Firefox"));
+        assert!(!matches(Exact, case, "fire", "Firefox"));
+        assert!(matches(Exact, case, "cost$", "cost$"));
+        assert!(matches(Prefix, case, "cost$", "cost$ report"));
+        assert!(matches(WordBoundary, case, "stu", "Visual Studio Code"));
+        assert!(matches(WordBoundary, case, "code", "VisualStudioCode"));
+        assert!(!matches(WordBoundary, case, "tudio", "Visual Studio"));
+        assert!(matches(Regex, case, "^fire.*x$", "Firefox"));
+        assert!(!matches(Regex, case, "fire(", "Firefox"));
+    }
+
+    #[test]
+    fn test_case_sensitivity() {
+        use CaseSensitivity::*;
+        assert!(matches(MatchKind::Substring, Smart, "fire", "Firefox"));
+        assert!(!matches(MatchKind::Substring, Smart, "FIre", "Firefox"));
+        assert!(!matches(MatchKind::Substring, Respect, "fire", "Firefox"));
+        assert!(!matches(MatchKind::Regex, Smart, "Fox", "Firefox"));
+        assert!(!matches(MatchKind::WordBoundary, Respect, "f", "Firefox"));
+    }
+
+    #[test]
+    fn test_indices() {
+        let mut matcher = Matcher::new(Config::DEFAULT);
+        let matching = Matching {
+            kind: MatchKind::Regex,
+            case: CaseSensitivity::Ignore,
+        };
+        let (_, indices) = matching
+            .query("é.f")
+            .match_text("Café fox", &mut matcher)
+            .unwrap();
+        assert_eq!(indices, vec![3, 4, 5]);
+        let (_, indices) = matching
+            .query("fox")
+            .match_text("👋🏿 e\u{301} foxes", &mut matcher)
+            .unwrap();
+        assert_eq!(indices, vec![4, 5, 6]);
+        let matching = Matching {
+            kind: MatchKind::WordBoundary,
+            ..matching
+        };
+        let (_, indices) = matching
+            .query("fox")
+            .match_text("👋🏿 e\u{301} foxes", &mut matcher)
+            .unwrap();
+        assert_eq!(indices, vec![4, 5, 6]);
+    }
+}
@@ -1,54 +1,83 @@
 mod apps;
+mod calc;
+mod combi;
+mod custom;
 mod dmenu;
+mod emoji;
+mod engine;
 mod files;
+mod matching;
+mod plugin;
 mod run;
 
 pub use apps::AppsMode;
+pub use calc::CalcMode;
+pub use combi::CombiMode;
+pub use custom::{CustomMode, CustomModeConfig, ItemFormat};
 pub use dmenu::DmenuMode;
+pub use emoji::{EmojiConfig, EmojiMode, SkinTone};
+pub use engine::{Engine, Injector};
 pub use files::*;
+pub use matching::{CaseSensitivity, MatchKind, Matching, Query};
+pub use plugin::{PROTOCOL_VERSION, PluginConfig, PluginMode};
 pub use run::RunMode;
 
 use crate::{item::Item, winit_app::EventHandle};
-use nucleo::{
-    Config, Matcher,
-    pattern::{CaseMatching, Normalization, Pattern},
-};
+use std::sync::Arc;
 
-pub trait Mode {
-    fn run(&mut self, event_handle: EventHandle);
-    fn update(&mut self, input: &str) -> Vec<Item>;
-    fn display_name(&self) -> &str;
-    fn cache_key(&self) -> Option<&'static str>;
+/// An item matched by a mode, together with its match score
+#[derive(Debug, Clone)]
+pub struct ScoredItem {
+    pub item: Arc<Item>,
+    pub score: u32,
+    /// Sorted indices of the matched characters in the text of the item
+    pub indices: Vec<u32>,
+    /// Name of the mode the item came from, only set by modes that combine other modes
+    pub source: Option<Arc<str>>,
 }
 
-pub trait SimpleMode {
-    fn display_name(&self) -> &str;
-    fn get_items(&mut self) -> &Vec<Item>;
+/// Progress of loading and matching the items of a mode
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Status {
+    /// Number of items that match the input
+    pub matched: u32,
+    /// Number of items loaded so far
+    pub total: u32,
+    /// Whether items are still being loaded or matched in the background
+    pub loading: bool,
 }
 
-impl<T: SimpleMode> Mode for T {
-    fn run(&mut self, _: EventHandle) {}
-
-    fn update(&mut self, input: &str) -> Vec<Item> {
-        fuzzy_match(input, self.get_items())
+pub trait Mode {
+    /// Starts loading the items, the mode has no items before it is run
+    fn run(&mut self, event_handle: EventHandle);
+    /// Matches the input, returns at most `limit` of the best matches
+    fn update(&mut self, input: &str, matching: Matching, limit: usize) -> Vec<ScoredItem>;
+    fn display_name(&self) -> &str;
+    fn cache_key(&self) -> Option<&str>;
+    /// Kind of matching the mode uses instead of the one of the `[matching]` config
+    fn match_kind(&self) -> Option<MatchKind> {
+        None
     }
-
-    fn display_name(&self) -> &str {
-        self.display_name()
+    /// Whether the result of the calculator is shown above the items when the input is a
+    /// calculation
+    fn inline_calc(&self) -> bool {
+        false
     }
-
-    fn cache_key(&self) -> Option<&'static str> {
+    fn status(&self) -> Status {
+        Status::default()
+    }
+    /// The part of the input that is matched against the history of the mode, `None` if the
+    /// history should not be searched for the input
+    fn history_query<'a>(&self, input: &'a str) -> Option<&'a str> {
+        Some(input)
+    }
+    /// Creates an item from the input itself, for when none of the items is wanted
+    fn custom_item(&self, _input: &str) -> Option<Item> {
         None
     }
-}
-
-pub fn fuzzy_match(input: &str, items: &[Item]) -> Vec<Item> {
-    let mut matcher = Matcher::new(Config::DEFAULT.match_paths());
-    Pattern::parse(input, CaseMatching::Ignore, Normalization::Smart)
-        .match_list(items, &mut matcher)
-        .into_iter()
-        // TODO: avoid cloning the item
-        .map(|(item, _)| item.clone())
-        .take(64) // Limit the results
-        .collect()
+    /// Whether the mode ranks its items itself, they are then shown in the order of the mode and
+    /// the history neither adds candidates nor boosts them
+    fn keeps_order(&self) -> bool {
+        false
+    }
 }
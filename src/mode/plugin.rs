@@ -0,0 +1,381 @@
+//! Modes that run in a separate process and talk to the launcher with JSON-RPC 2.0 over stdio
+//!
+//! Every message is a single line of JSON. The launcher writes to the stdin of the plugin:
+//!
+//! - `initialize` request with `{"protocol_version": 1}`, the plugin must respond with the
+//!   protocol version it implements before any query is answered
+//! - `query` request with `{"input": "..."}` whenever the input changes, starting with the empty
+//!   input as soon as the mode runs
+//! - `cancel` notification with `{"id": <id of the query>}` when a query that has not been
+//!   responded to yet is replaced by a newer one, the plugin should stop working on it
+//!
+//! The plugin writes to its stdout:
+//!
+//! - `items` notifications with `{"id": <id of the query>, "items": [...]}` to stream items while
+//!   a query is answered, they are added to the items of the query in order
+//! - a response to every `query` request, its result may contain a last batch of `items` and
+//!   finishes the query, an error response finishes it as well
+//!
+//! An item is an object like `{"text": "Firefox", "action": {"type": "exec", "program":
+//! "firefox"}}`. The action is optional and one of `print` (the default, prints the text to
+//! stdout), `shell` with a `command`, or `exec` with a `program`, its `args` and whether to run it
+//! in a `terminal`. The launcher shows the items in the order they are sent, messages for queries
+//! that are not the current one are ignored.
+
+use super::{Matching, Mode, ScoredItem, Status, engine::UPDATE_INTERVAL};
+use crate::{
+    item::{Action, Item},
+    winit_app::EventHandle,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::{
+    io::{BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::{Arc, Mutex},
+    thread,
+    time::Instant,
+};
+
+pub const PROTOCOL_VERSION: u32 = 1;
+/// Id of the `initialize` request, the ids of the queries start after it
+const INITIALIZE_ID: u64 = 0;
+
+/// A `[[plugin]]` table of the config
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PluginConfig {
+    /// Used to select the mode with `-m`
+    pub name: String,
+    /// Shell command that starts the plugin
+    pub command: String,
+    /// Key of the history of the mode, no history is kept without a key
+    pub cache_key: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Request<'a> {
+    jsonrpc: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<u64>,
+    method: &'a str,
+    params: Value,
+}
+
+/// A request, a response or a notification sent by the plugin
+#[derive(Deserialize)]
+struct Message {
+    id: Option<u64>,
+    method: Option<String>,
+    #[serde(default)]
+    params: Value,
+    #[serde(default)]
+    result: Value,
+    error: Option<RpcError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct ItemsParams {
+    id: u64,
+    items: Vec<PluginItem>,
+}
+
+#[derive(Default, Deserialize)]
+struct QueryResult {
+    #[serde(default)]
+    items: Vec<PluginItem>,
+}
+
+#[derive(Deserialize)]
+struct InitializeResult {
+    protocol_version: u32,
+}
+
+#[derive(Deserialize)]
+struct PluginItem {
+    text: String,
+    #[serde(default)]
+    action: PluginAction,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+enum PluginAction {
+    #[default]
+    Print,
+    Shell {
+        command: String,
+    },
+    Exec {
+        program: String,
+        #[serde(default)]
+        args: Vec<String>,
+        #[serde(default)]
+        terminal: bool,
+    },
+}
+
+impl From<PluginItem> for Item {
+    fn from(item: PluginItem) -> Self {
+        let action = match item.action {
+            PluginAction::Print => Action::Selection,
+            PluginAction::Shell { command } => Action::Shell { command },
+            PluginAction::Exec {
+                program,
+                args,
+                terminal,
+            } => Action::Exec {
+                program,
+                args,
+                terminal,
+            },
+        };
+        Item::new(item.text, action)
+    }
+}
+
+/// The items of the current query, shared with the thread that reads the output of the plugin
+#[derive(Default)]
+struct Results {
+    /// Id of the current query
+    query: u64,
+    items: Vec<Arc<Item>>,
+    /// Whether the plugin has not finished the current query yet
+    pending: bool,
+    /// Set when the plugin exited or broke the protocol, it is not queried anymore
+    failed: bool,
+}
+
+/// A mode whose items are provided by a plugin process, the protocol is described in the docs of
+/// the `plugin` module
+pub struct PluginMode {
+    config: PluginConfig,
+    child: Option<Child>,
+    stdin: Option<ChildStdin>,
+    results: Arc<Mutex<Results>>,
+    /// The input of the current query
+    input: String,
+    next_id: u64,
+}
+
+impl PluginMode {
+    pub fn new(config: PluginConfig) -> Self {
+        Self {
+            config,
+            child: None,
+            stdin: None,
+            results: Arc::default(),
+            input: String::new(),
+            next_id: INITIALIZE_ID + 1,
+        }
+    }
+
+    fn send(&mut self, id: Option<u64>, method: &str, params: Value) {
+        let Some(stdin) = &mut self.stdin else {
+            return;
+        };
+        let request = Request {
+            jsonrpc: "2.0",
+            id,
+            method,
+            params,
+        };
+        let mut line = serde_json::to_string(&request).expect("requests are valid JSON");
+        line.push('\n');
+        if let Err(e) = stdin.write_all(line.as_bytes()).and_then(|_| stdin.flush()) {
+            log::error!(
+                "plugin '{}': failed to send {method}: {e}",
+                self.config.name
+            );
+            self.stdin = None;
+            self.results.lock().unwrap().failed = true;
+        }
+    }
+
+    /// Replaces the current query, cancelling it if the plugin is still working on it
+    fn query(&mut self, input: &str) {
+        let id = self.next_id;
+        self.next_id += 1;
+        let cancelled = {
+            let mut results = self.results.lock().unwrap();
+            if results.failed {
+                return;
+            }
+            let cancelled = results.pending.then_some(results.query);
+            *results = Results {
+                query: id,
+                pending: true,
+                ..Results::default()
+            };
+            cancelled
+        };
+        if let Some(cancelled) = cancelled {
+            self.send(None, "cancel", json!({ "id": cancelled }));
+        }
+        self.input = input.to_string();
+        self.send(Some(id), "query", json!({ "input": input }));
+    }
+}
+
+impl Mode for PluginMode {
+    fn display_name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn run(&mut self, event_handle: EventHandle) {
+        let mut child = match Command::new("sh")
+            .args(["-c", &self.config.command])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(e) => {
+                log::error!("plugin '{}': failed to start: {e}", self.config.name);
+                self.results.lock().unwrap().failed = true;
+                return;
+            }
+        };
+        self.stdin = child.stdin.take();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        self.child = Some(child);
+
+        let name = self.config.name.clone();
+        let results = self.results.clone();
+        thread::spawn(move || {
+            let mut last_update = Instant::now() - UPDATE_INTERVAL;
+            for line in stdout.lines().map_while(Result::ok) {
+                let handled = match serde_json::from_str(&line) {
+                    Ok(message) => handle_message(message, &results),
+                    Err(e) => Err(e.into()),
+                };
+                if let Err(e) = handled {
+                    log::error!("plugin '{name}': invalid message '{line}': {e}");
+                }
+                // streamed items are throttled like the updates of the engine, the message that
+                // finishes a query is never throttled
+                let streaming = results.lock().unwrap().pending;
+                if !streaming || last_update.elapsed() > UPDATE_INTERVAL {
+                    last_update = Instant::now();
+                    event_handle.send_update();
+                }
+            }
+            log::info!("plugin '{name}' exited");
+            let mut results = results.lock().unwrap();
+            results.failed = true;
+            results.pending = false;
+            event_handle.send_update();
+        });
+        self.send(
+            Some(INITIALIZE_ID),
+            "initialize",
+            json!({ "protocol_version": PROTOCOL_VERSION }),
+        );
+        self.query("");
+    }
+
+    fn update(&mut self, input: &str, _matching: Matching, limit: usize) -> Vec<ScoredItem> {
+        if input != self.input {
+            self.query(input);
+        }
+        let results = self.results.lock().unwrap();
+        let count = results.items.len();
+        results
+            .items
+            .iter()
+            .take(limit)
+            .enumerate()
+            .map(|(i, item)| ScoredItem {
+                item: item.clone(),
+                // keep the order of the plugin
+                score: (count - i) as u32,
+                indices: Vec::new(),
+                source: None,
+            })
+            .collect()
+    }
+
+    fn cache_key(&self) -> Option<&str> {
+        self.config.cache_key.as_deref()
+    }
+
+    fn keeps_order(&self) -> bool {
+        true
+    }
+
+    fn status(&self) -> Status {
+        let results = self.results.lock().unwrap();
+        Status {
+            matched: results.items.len() as u32,
+            total: results.items.len() as u32,
+            loading: results.pending,
+        }
+    }
+}
+
+impl Drop for PluginMode {
+    fn drop(&mut self) {
+        // closing stdin asks the plugin to exit, killing it makes sure it does
+        self.stdin = None;
+        if let Some(child) = &mut self.child {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+fn handle_message(message: Message, results: &Mutex<Results>) -> anyhow::Result<()> {
+    let mut results = results.lock().unwrap();
+    match (message.method.as_deref(), message.id) {
+        (Some("items"), _) => {
+            let params: ItemsParams = serde_json::from_value(message.params)?;
+            if params.id == results.query && results.pending {
+                results
+                    .items
+                    .extend(params.items.into_iter().map(|item| Arc::new(item.into())));
+            }
+        }
+        (Some(method), _) => anyhow::bail!("unknown method '{method}'"),
+        (None, Some(INITIALIZE_ID)) => {
+            if let Some(error) = message.error {
+                results.failed = true;
+                results.pending = false;
+                anyhow::bail!("initialize failed: {error:?}");
+            }
+            let result: InitializeResult = serde_json::from_value(message.result)?;
+            if result.protocol_version != PROTOCOL_VERSION {
+                results.failed = true;
+                results.pending = false;
+                anyhow::bail!(
+                    "unsupported protocol version {}, expected {PROTOCOL_VERSION}",
+                    result.protocol_version
+                );
+            }
+        }
+        (None, Some(id)) => {
+            if id != results.query {
+                // the response to a cancelled query
+                return Ok(());
+            }
+            results.pending = false;
+            if let Some(error) = message.error {
+                anyhow::bail!("query failed with code {}: {}", error.code, error.message);
+            }
+            let result: QueryResult = match message.result {
+                Value::Null => QueryResult::default(),
+                result => serde_json::from_value(result)?,
+            };
+            results
+                .items
+                .extend(result.items.into_iter().map(|item| Arc::new(item.into())));
+        }
+        (None, None) => anyhow::bail!("message is neither a response nor a notification"),
+    }
+    Ok(())
+}
@@ -1,46 +1,85 @@
-use super::SimpleMode;
+use super::{Engine, Matching, Mode, ScoredItem, Status};
 use crate::{
     file_finder,
     item::{Action, Item},
+    winit_app::EventHandle,
 };
-use std::path::PathBuf;
+use std::{path::PathBuf, thread};
 
 pub struct RunMode {
-    executables: Vec<Item>,
+    engine: Option<Engine>,
 }
 
 impl RunMode {
     pub fn load() -> Self {
-        // TODO: Filter on executable files
-        let path_dirs = std::env::var("PATH")
-            .expect("PATH is not set")
-            .split(':')
-            .map(PathBuf::from)
-            .collect::<Vec<PathBuf>>();
-        Self {
-            executables: file_finder::find_files_from_dirs(&path_dirs, &|_| true)
-                .into_iter()
-                .map(|path| {
-                    Item::new(
-                        path.file_name().unwrap().to_string_lossy().to_string(),
-                        Action::Exec {
-                            program: path.to_string_lossy().to_string(),
-                            args: Vec::new(),
-                            terminal: false,
-                        },
-                    )
-                })
-                .collect(),
-        }
+        Self { engine: None }
     }
 }
 
-impl SimpleMode for RunMode {
+impl Mode for RunMode {
     fn display_name(&self) -> &str {
         "Run"
     }
 
-    fn get_items(&mut self) -> &Vec<Item> {
-        &self.executables
+    fn run(&mut self, event_handle: EventHandle) {
+        let mut engine = Engine::new(event_handle);
+        let mut injector = engine.injector();
+        self.engine = Some(engine);
+
+        thread::spawn(move || {
+            // TODO: Filter on executable files
+            let path_dirs = std::env::var("PATH")
+                .expect("PATH is not set")
+                .split(':')
+                .map(PathBuf::from)
+                .collect::<Vec<PathBuf>>();
+            injector.extend(
+                file_finder::find_files_from_dirs(&path_dirs, &|_| true)
+                    .into_iter()
+                    .map(|path| {
+                        Item::new(
+                            path.file_name().unwrap().to_string_lossy().to_string(),
+                            Action::Exec {
+                                program: path.to_string_lossy().to_string(),
+                                args: Vec::new(),
+                                terminal: false,
+                            },
+                        )
+                    }),
+            );
+        });
+    }
+
+    fn update(&mut self, input: &str, matching: Matching, limit: usize) -> Vec<ScoredItem> {
+        self.engine
+            .as_mut()
+            .map(|engine| engine.update(input, matching, limit))
+            .unwrap_or_default()
+    }
+
+    fn cache_key(&self) -> Option<&str> {
+        None
+    }
+
+    fn inline_calc(&self) -> bool {
+        true
+    }
+
+    fn status(&self) -> Status {
+        self.engine.as_ref().map(Engine::status).unwrap_or_default()
+    }
+
+    /// Runs the input as a command, the first word is the program
+    fn custom_item(&self, input: &str) -> Option<Item> {
+        let mut words = input.split_whitespace().map(str::to_string);
+        let program = words.next()?;
+        Some(Item::new(
+            input.to_string(),
+            Action::Exec {
+                program,
+                args: words.collect(),
+                terminal: false,
+            },
+        ))
     }
 }
@@ -0,0 +1,174 @@
+use crate::{
+    item::Item,
+    mode::ScoredItem,
+    recent::{self, RecentItem, RecentItems},
+};
+use indexmap::IndexMap;
+use serde::Serialize;
+use std::{cmp::Reverse, collections::HashMap, sync::Arc};
+
+/// Score boost per unit of (logarithmic) frecency
+const FRECENCY_WEIGHT: f64 = 32.0;
+/// Score boost per unit of (logarithmic) query association, see [`RecentItem::query_score`]
+const QUERY_WEIGHT: f64 = 48.0;
+
+#[derive(Debug, Serialize)]
+pub struct Match {
+    pub item: Arc<Item>,
+    /// The match score including the boost from the history of the item
+    pub score: u32,
+    /// Indices of the matched characters in the text of the item
+    pub indices: Vec<u32>,
+    pub recent: bool,
+    pub pinned: bool,
+    /// Name of the mode the item came from, see [`ScoredItem::source`]
+    pub source: Option<Arc<str>>,
+}
+
+/// Ranks the candidates of all sources (e.g. the mode and the history) in a single list
+/// Every candidate gets the history boost of its item, no matter which source it came from, so
+/// a weak match on a recent item does not beat a strong match on an item without history
+pub struct Ranker<'a> {
+    recent: HashMap<&'a Item, &'a RecentItem>,
+    input: &'a str,
+    now: u64,
+    candidates: IndexMap<Arc<Item>, Match>,
+    keep_order: bool,
+}
+
+impl<'a> Ranker<'a> {
+    pub fn new(recent: &'a RecentItems, cache_key: Option<&str>, input: &'a str) -> Self {
+        Self {
+            recent: cache_key
+                .map(|key| recent.get_recent(key))
+                .unwrap_or_default(),
+            input,
+            now: recent::now(),
+            candidates: IndexMap::new(),
+            keep_order: false,
+        }
+    }
+
+    /// Keeps the order in which the candidates are added instead of ranking them, see
+    /// [`Mode::keeps_order`](crate::mode::Mode::keeps_order)
+    pub fn keep_order(mut self, keep_order: bool) -> Self {
+        self.keep_order = keep_order;
+        self
+    }
+
+    /// Adds a candidate, an item that was already added by another source keeps the best score
+    pub fn add(&mut self, scored: ScoredItem) {
+        let ScoredItem {
+            item,
+            score,
+            indices,
+            source,
+        } = scored;
+        let recent_item = self.recent.get(item.as_ref()).copied();
+        let boost = match recent_item {
+            Some(recent_item) if !self.keep_order => {
+                history_boost(recent_item, self.input, self.now)
+            }
+            _ => 0,
+        };
+        let score = score + boost;
+        if let Some(r#match) = self.candidates.get_mut(&item) {
+            // the history does not know the source of an item
+            r#match.source = r#match.source.take().or(source);
+            if score > r#match.score {
+                r#match.score = score;
+                r#match.indices = indices;
+            }
+            return;
+        }
+        let r#match = Match {
+            item: item.clone(),
+            score,
+            indices,
+            recent: recent_item.is_some(),
+            pinned: recent_item.is_some_and(|r| r.pinned()),
+            source,
+        };
+        self.candidates.insert(item, r#match);
+    }
+
+    /// Sorts the candidates once: pinned items first, then by score and then by the length of the
+    /// text, the sort is stable so remaining ties keep the order in which they were added
+    pub fn finish(self) -> Vec<Match> {
+        let mut matches: Vec<Match> = self.candidates.into_values().collect();
+        if !self.keep_order {
+            matches.sort_by_key(|m| (Reverse(m.pinned), Reverse(m.score), m.item.text.len()));
+        }
+        matches
+    }
+}
+
+/// Computes the score boost of an item from its history, the logarithms prevent frequently used
+/// items from dominating the match score
+fn history_boost(recent_item: &RecentItem, input: &str, now: u64) -> u32 {
+    let frecency = recent_item.frecency(now).ln_1p() * FRECENCY_WEIGHT;
+    let query = recent_item.query_score(input).ln_1p() * QUERY_WEIGHT;
+    (frecency + query) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scored(text: &str, score: u32) -> ScoredItem {
+        ScoredItem {
+            item: Arc::new(Item::new_selection(text.to_string())),
+            score,
+            indices: Vec::new(),
+            source: None,
+        }
+    }
+
+    #[test]
+    fn test_single_ranking() {
+        let mut recent = RecentItems::default();
+        recent
+            .insert_and_save("test", Item::new_selection("weak".to_string()).into(), "")
+            .unwrap();
+        let mut ranker = Ranker::new(&recent, Some("test"), "input");
+        // the history source is added first, but the stronger match of the mode still wins
+        ranker.add(scored("weak", 10));
+        ranker.add(scored("strong", 200));
+        ranker.add(scored("weak", 12));
+        let matches = ranker.finish();
+        let texts: Vec<&str> = matches.iter().map(|m| m.item.text.as_str()).collect();
+        assert_eq!(texts, vec!["strong", "weak"]);
+        assert!(matches[1].recent);
+        assert!(matches[1].score > 12);
+    }
+
+    #[test]
+    fn test_tie_breaking() {
+        let recent = RecentItems::default();
+        let mut ranker = Ranker::new(&recent, None, "");
+        ranker.add(scored("longer", 50));
+        ranker.add(scored("b", 50));
+        ranker.add(scored("a", 50));
+        let texts: Vec<String> = ranker
+            .finish()
+            .into_iter()
+            .map(|m| m.item.text.clone())
+            .collect();
+        assert_eq!(texts, vec!["b", "a", "longer"]);
+    }
+
+    #[test]
+    fn test_keep_order() {
+        let mut recent = RecentItems::default();
+        recent
+            .insert_and_save("test", Item::new_selection("second".to_string()).into(), "")
+            .unwrap();
+        let mut ranker = Ranker::new(&recent, Some("test"), "").keep_order(true);
+        ranker.add(scored("first", 2));
+        ranker.add(scored("second", 1));
+        let matches = ranker.finish();
+        let texts: Vec<&str> = matches.iter().map(|m| m.item.text.as_str()).collect();
+        assert_eq!(texts, vec!["first", "second"]);
+        assert!(matches[1].recent);
+    }
+}
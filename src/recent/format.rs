@@ -0,0 +1,419 @@
+//! On-disk format of the recent items file
+//!
+//! The file starts with a magic number and the format version, followed by the postcard encoded
+//! [`RecentItems`]. Postcard is not self-describing, so any change to the serialized types,
+//! including [`Item`] and [`Action`], requires a new format version: copy the current types into
+//! a module for the old version, bump [`CURRENT_VERSION`] and add a migration to [`decode`].
+
+use super::{RecentItem, RecentItems};
+use crate::item::{Action, Item};
+use serde::de::DeserializeOwned;
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+    sync::Arc,
+};
+
+const MAGIC: &[u8; 4] = b"LNCR";
+pub const CURRENT_VERSION: u32 = 4;
+
+#[derive(Debug)]
+pub enum DecodeError {
+    /// The file was written by a newer version of launchr
+    UnsupportedVersion(u32),
+    Corrupt(postcard::Error),
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            DecodeError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported format version {version} (latest supported is {CURRENT_VERSION})"
+            ),
+            DecodeError::Corrupt(e) => write!(f, "corrupt file: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl From<postcard::Error> for DecodeError {
+    fn from(value: postcard::Error) -> Self {
+        DecodeError::Corrupt(value)
+    }
+}
+
+pub fn encode(recent: &RecentItems) -> postcard::Result<Vec<u8>> {
+    let buf = postcard::to_extend(&CURRENT_VERSION, MAGIC.to_vec())?;
+    postcard::to_extend(recent, buf)
+}
+
+/// Decodes the file and migrates it to the current version
+pub fn decode(buf: &[u8]) -> Result<RecentItems, DecodeError> {
+    let Some(buf) = buf.strip_prefix(MAGIC) else {
+        log::info!("migrating recent items from the unversioned format");
+        return Ok(from_bytes_exact::<v0::RecentItems>(buf)?.into());
+    };
+    let (version, buf) = postcard::take_from_bytes::<u32>(buf)?;
+    match version {
+        1 => Ok(from_bytes_exact::<v1::RecentItems>(buf)?.into()),
+        2 => Ok(from_bytes_exact::<v2::RecentItems>(buf)?.into()),
+        3 => Ok(from_bytes_exact::<v3::RecentItems>(buf)?.into()),
+        CURRENT_VERSION => from_bytes_exact(buf),
+        version => Err(DecodeError::UnsupportedVersion(version)),
+    }
+}
+
+/// Deserializes the buffer, which must not contain any trailing bytes
+fn from_bytes_exact<T: DeserializeOwned>(buf: &[u8]) -> Result<T, DecodeError> {
+    match postcard::take_from_bytes(buf)? {
+        (res, []) => Ok(res),
+        _ => Err(postcard::Error::DeserializeBadEncoding.into()),
+    }
+}
+
+/// The unversioned format, a list of items per mode
+mod v0 {
+    use serde::Deserialize;
+    use std::{collections::BTreeMap, path::PathBuf};
+
+    pub type RecentItems = BTreeMap<String, Vec<Item>>;
+
+    #[derive(Deserialize)]
+    pub struct Item {
+        pub text: String,
+        pub action: Action,
+    }
+
+    #[derive(Deserialize)]
+    pub enum Action {
+        Selection,
+        Exec {
+            program: String,
+            args: Vec<String>,
+            terminal: bool,
+        },
+        File {
+            path: PathBuf,
+            is_dir: bool,
+        },
+    }
+}
+
+/// Added launch statistics and remembered queries
+mod v1 {
+    use super::v0::Item;
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Deserialize)]
+    pub struct RecentItems {
+        pub items: BTreeMap<String, Vec<RecentItem>>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct RecentItem {
+        pub item: Item,
+        pub count: u32,
+        pub launches: Vec<u64>,
+        pub queries: Vec<(String, u32)>,
+    }
+}
+
+/// Added pinned items
+mod v2 {
+    use super::v0::Item;
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Deserialize)]
+    pub struct RecentItems {
+        pub items: BTreeMap<String, Vec<RecentItem>>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct RecentItem {
+        pub item: Item,
+        pub count: u32,
+        pub launches: Vec<u64>,
+        pub queries: Vec<(String, u32)>,
+        pub pinned: bool,
+    }
+}
+
+/// Added shell commands as an action
+mod v3 {
+    use serde::Deserialize;
+    use std::{collections::BTreeMap, path::PathBuf};
+
+    #[derive(Deserialize)]
+    pub struct RecentItems {
+        pub items: BTreeMap<String, Vec<RecentItem>>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct RecentItem {
+        pub item: Item,
+        pub count: u32,
+        pub launches: Vec<u64>,
+        pub queries: Vec<(String, u32)>,
+        pub pinned: bool,
+    }
+
+    #[derive(Deserialize)]
+    pub struct Item {
+        pub text: String,
+        pub action: Action,
+    }
+
+    #[derive(Deserialize)]
+    pub enum Action {
+        Selection,
+        Exec {
+            program: String,
+            args: Vec<String>,
+            terminal: bool,
+        },
+        File {
+            path: PathBuf,
+            is_dir: bool,
+        },
+        Shell {
+            command: String,
+        },
+    }
+}
+
+impl From<v0::Item> for Item {
+    fn from(value: v0::Item) -> Self {
+        let action = match value.action {
+            v0::Action::Selection => Action::Selection,
+            v0::Action::Exec {
+                program,
+                args,
+                terminal,
+            } => Action::Exec {
+                program,
+                args,
+                terminal,
+            },
+            v0::Action::File { path, is_dir } => Action::File { path, is_dir },
+        };
+        Item::new(value.text, action)
+    }
+}
+
+impl From<v3::Item> for Item {
+    fn from(value: v3::Item) -> Self {
+        let action = match value.action {
+            v3::Action::Selection => Action::Selection,
+            v3::Action::Exec {
+                program,
+                args,
+                terminal,
+            } => Action::Exec {
+                program,
+                args,
+                terminal,
+            },
+            v3::Action::File { path, is_dir } => Action::File { path, is_dir },
+            v3::Action::Shell { command } => Action::Shell { command },
+        };
+        Item::new(value.text, action)
+    }
+}
+
+impl From<v0::RecentItems> for RecentItems {
+    fn from(value: v0::RecentItems) -> Self {
+        // the launch times are unknown, so all items are treated as launched once just now
+        let now = super::now();
+        let items: BTreeMap<String, Vec<RecentItem>> = value
+            .into_iter()
+            .map(|(key, items)| {
+                let items = items
+                    .into_iter()
+                    .map(|item| {
+                        let mut recent_item = RecentItem::new(Arc::new(item.into()));
+                        recent_item.launch(now, "");
+                        recent_item
+                    })
+                    .collect();
+                (key, items)
+            })
+            .collect();
+        RecentItems { path: None, items }
+    }
+}
+
+impl From<v1::RecentItems> for RecentItems {
+    fn from(value: v1::RecentItems) -> Self {
+        let items = value
+            .items
+            .into_iter()
+            .map(|(key, items)| {
+                let items = items
+                    .into_iter()
+                    .map(|i| RecentItem {
+                        item: Arc::new(i.item.into()),
+                        count: i.count,
+                        launches: i.launches,
+                        queries: i.queries,
+                        pinned: false,
+                    })
+                    .collect();
+                (key, items)
+            })
+            .collect();
+        RecentItems { path: None, items }
+    }
+}
+
+impl From<v2::RecentItems> for RecentItems {
+    fn from(value: v2::RecentItems) -> Self {
+        let items = value
+            .items
+            .into_iter()
+            .map(|(key, items)| {
+                let items = items
+                    .into_iter()
+                    .map(|i| RecentItem {
+                        item: Arc::new(i.item.into()),
+                        count: i.count,
+                        launches: i.launches,
+                        queries: i.queries,
+                        pinned: i.pinned,
+                    })
+                    .collect();
+                (key, items)
+            })
+            .collect();
+        RecentItems { path: None, items }
+    }
+}
+
+impl From<v3::RecentItems> for RecentItems {
+    fn from(value: v3::RecentItems) -> Self {
+        let items = value
+            .items
+            .into_iter()
+            .map(|(key, items)| {
+                let items = items
+                    .into_iter()
+                    .map(|i| RecentItem {
+                        item: Arc::new(i.item.into()),
+                        count: i.count,
+                        launches: i.launches,
+                        queries: i.queries,
+                        pinned: i.pinned,
+                    })
+                    .collect();
+                (key, items)
+            })
+            .collect();
+        RecentItems { path: None, items }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn expected_items() -> Vec<(&'static str, Item)> {
+        vec![
+            (
+                "apps",
+                Item::new(
+                    "Firefox".to_string(),
+                    Action::Exec {
+                        program: "firefox".to_string(),
+                        args: vec!["--new-window".to_string()],
+                        terminal: false,
+                    },
+                ),
+            ),
+            (
+                "files",
+                Item::new(
+                    "notes.txt".to_string(),
+                    Action::File {
+                        path: PathBuf::from("/home/user/notes.txt"),
+                        is_dir: false,
+                    },
+                ),
+            ),
+        ]
+    }
+
+    fn assert_fixture(recent: &RecentItems) {
+        for (key, item) in expected_items() {
+            assert_eq!(recent.get_items(key), vec![Arc::new(item)]);
+        }
+    }
+
+    #[test]
+    fn test_decode_v0() {
+        let recent = decode(include_bytes!("../../tests/fixtures/recent-v0.bin")).unwrap();
+        assert_fixture(&recent);
+    }
+
+    #[test]
+    fn test_decode_v1() {
+        let recent = decode(include_bytes!("../../tests/fixtures/recent-v1.bin")).unwrap();
+        assert_fixture(&recent);
+        let firefox = &recent.items["apps"][0];
+        assert_eq!(firefox.count, 3);
+        assert_eq!(firefox.query_score("fi"), 2.0);
+    }
+
+    #[test]
+    fn test_decode_v2() {
+        let recent = decode(include_bytes!("../../tests/fixtures/recent-v2.bin")).unwrap();
+        assert_fixture(&recent);
+        assert!(recent.items["apps"][0].pinned);
+        assert!(!recent.items["files"][0].pinned);
+    }
+
+    #[test]
+    fn test_decode_v3() {
+        let recent = decode(include_bytes!("../../tests/fixtures/recent-v3.bin")).unwrap();
+        assert_fixture(&recent);
+        assert!(recent.items["apps"][0].pinned);
+        let greet = &recent.items["greet"][0].item;
+        assert!(
+            matches!(&greet.action, Action::Shell { command } if command == "notify-send hello")
+        );
+    }
+
+    #[test]
+    fn test_decode_v4() {
+        let recent = decode(include_bytes!("../../tests/fixtures/recent-v4.bin")).unwrap();
+        assert_fixture(&recent);
+        assert_eq!(
+            recent.items["calc"][0].item.action,
+            Action::Copy {
+                text: "1024".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let mut recent = RecentItems::default();
+        for (key, item) in expected_items() {
+            recent.insert(key, item.into(), "query");
+        }
+        let decoded = decode(&encode(&recent).unwrap()).unwrap();
+        assert_fixture(&decoded);
+    }
+
+    #[test]
+    fn test_newer_version() {
+        let buf = postcard::to_extend(&(CURRENT_VERSION + 1), MAGIC.to_vec()).unwrap();
+        assert!(matches!(
+            decode(&buf),
+            Err(DecodeError::UnsupportedVersion(_))
+        ));
+    }
+}
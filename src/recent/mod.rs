@@ -0,0 +1,627 @@
+mod format;
+
+use crate::item::{Action, Item};
+use format::DecodeError;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Write},
+    os::fd::AsRawFd,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct RecentItems {
+    /// The file the items are saved to, if any
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    items: BTreeMap<String, Vec<RecentItem>>,
+}
+
+/// An item in the history together with its launch statistics
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RecentItem {
+    pub item: Arc<Item>,
+    /// Total number of times the item was launched
+    count: u32,
+    /// Unix timestamps (in seconds) of the most recent launches, newest first
+    launches: Vec<u64>,
+    /// The inputs for which the item was chosen, with the number of times it was chosen for them
+    queries: Vec<(String, u32)>,
+    /// Pinned items are always shown first and are never evicted
+    pinned: bool,
+}
+
+const STATE_DIR_NAME: &str = env!("CARGO_CRATE_NAME");
+const RECENT_FILE_NAME: &str = "recent";
+const LOCK_EXTENSION: &str = "lock";
+const TMP_EXTENSION: &str = "tmp";
+const CORRUPT_EXTENSION: &str = "corrupt";
+const MAX_RECENT_ITEMS: usize = 128;
+/// Number of launch timestamps that are kept to compute the frecency
+const MAX_LAUNCHES: usize = 10;
+/// Time after which the weight of a launch is halved
+const HALF_LIFE_SECS: f64 = 7.0 * 24.0 * 60.0 * 60.0;
+/// Number of distinct queries that are remembered per item
+const MAX_QUERIES: usize = 8;
+
+/// Returns the current time as a unix timestamp in seconds
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl RecentItem {
+    fn new(item: Arc<Item>) -> Self {
+        Self {
+            item,
+            count: 0,
+            launches: Vec::new(),
+            queries: Vec::new(),
+            pinned: false,
+        }
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn pinned(&self) -> bool {
+        self.pinned
+    }
+
+    fn launch(&mut self, now: u64, query: &str) {
+        self.count = self.count.saturating_add(1);
+        self.launches.insert(0, now);
+        self.launches.truncate(MAX_LAUNCHES);
+
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return;
+        }
+        let count = match self.queries.iter().position(|(q, _)| *q == query) {
+            Some(index) => self.queries.remove(index).1,
+            None => 0,
+        };
+        // the most recently used queries are kept at the front
+        self.queries.insert(0, (query, count.saturating_add(1)));
+        self.queries.truncate(MAX_QUERIES);
+    }
+
+    /// Frecency score of the item, combining how often and how recently it was launched
+    /// Every remembered launch is weighted by an exponential decay, the average weight is then
+    /// scaled by the total number of launches
+    pub fn frecency(&self, now: u64) -> f64 {
+        if self.launches.is_empty() {
+            return 0.0;
+        }
+        let total_weight: f64 = self
+            .launches
+            .iter()
+            .map(|&time| 0.5f64.powf(now.saturating_sub(time) as f64 / HALF_LIFE_SECS))
+            .sum();
+        self.count as f64 * total_weight / self.launches.len() as f64
+    }
+
+    /// How strongly the input is associated with the item
+    /// A remembered query counts when it is a prefix of the input, so extending an abbreviation
+    /// keeps the association
+    /// The closer the length of the query to the input, the higher the score
+    pub fn query_score(&self, input: &str) -> f64 {
+        let input = input.trim().to_lowercase();
+        if input.is_empty() {
+            return 0.0;
+        }
+        self.queries
+            .iter()
+            .filter(|(query, _)| input.starts_with(query.as_str()))
+            .map(|(query, count)| *count as f64 * query.len() as f64 / input.len() as f64)
+            .sum()
+    }
+}
+
+impl RecentItems {
+    pub fn load_or_default() -> anyhow::Result<Self> {
+        let state_dir = dirs::state_dir().unwrap().join(STATE_DIR_NAME);
+        fs::create_dir_all(&state_dir)?;
+        Self::load_from(state_dir.join(RECENT_FILE_NAME))
+    }
+
+    /// Loads the recent items from the given file, which is also used to save them
+    /// A corrupt file is moved aside, so the recent items start fresh instead of failing to load
+    /// Items that no longer exist are pruned in memory, the file keeps them until the next save
+    pub fn load_from(path: PathBuf) -> anyhow::Result<Self> {
+        let res = {
+            let _lock = FileLock::shared(&path)?;
+            Self::read_file(&path)
+        };
+        let res = match res {
+            Err(e) if is_corrupt(&e) => {
+                // moving the file aside requires the exclusive lock, the file is read again because
+                // it may have been replaced in the meantime
+                let _lock = FileLock::exclusive(&path)?;
+                Self::read(&path)
+            }
+            res => res,
+        };
+        match res {
+            Ok(mut res) => {
+                res.prune(None);
+                res.path = Some(path);
+                Ok(res)
+            }
+            Err(e) if e.is::<DecodeError>() => {
+                log::error!("failed to read recent items, history will not be saved: {e}");
+                Ok(Self::default())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Reads the file without locking it, a corrupt file is moved aside
+    /// The caller should hold the exclusive lock
+    fn read(path: &Path) -> anyhow::Result<Self> {
+        match Self::read_file(path) {
+            Err(e) if is_corrupt(&e) => {
+                let backup_path = path.with_extension(CORRUPT_EXTENSION);
+                log::error!(
+                    "failed to read recent items, moving the file to '{}' and starting fresh: {e}",
+                    backup_path.display()
+                );
+                if let Err(e) = fs::rename(path, &backup_path) {
+                    log::error!("failed to move corrupt recent items file: {e}");
+                }
+                Ok(Self::default())
+            }
+            res => res,
+        }
+    }
+
+    /// Reads the file without locking it, the caller should hold the lock
+    fn read_file(path: &Path) -> anyhow::Result<Self> {
+        let start_instant = Instant::now();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let mut file = File::open(path)?;
+        let mut buf = Vec::new();
+        let file_len = file.read_to_end(&mut buf)?;
+        let res = format::decode(&buf)?;
+        log::info!(
+            "loaded recent items in {:?} ({file_len} bytes)",
+            start_instant.elapsed()
+        );
+        Ok(res)
+    }
+
+    /// Inserts an item that was chosen for the given query and saves the recent items
+    /// The other items of the mode that no longer exist are pruned at the same time
+    pub fn insert_and_save(
+        &mut self,
+        key: &str,
+        item: Arc<Item>,
+        query: &str,
+    ) -> anyhow::Result<()> {
+        self.modify_and_save(|recent| {
+            recent.insert(key, item, query);
+            recent.prune(Some(key));
+        })
+    }
+
+    fn insert(&mut self, key: &str, item: Arc<Item>, query: &str) {
+        let now = now();
+        let mode_items = self.items.entry(key.to_string()).or_default();
+        let index = match mode_items.iter().position(|i| i.item == item) {
+            Some(index) => index,
+            None => {
+                mode_items.push(RecentItem::new(item));
+                mode_items.len() - 1
+            }
+        };
+        mode_items[index].launch(now, query);
+        // keep the pinned items and the items with the highest frecency
+        mode_items.sort_by(|a, b| {
+            b.pinned
+                .cmp(&a.pinned)
+                .then(b.frecency(now).total_cmp(&a.frecency(now)))
+        });
+        mode_items.truncate(MAX_RECENT_ITEMS);
+    }
+
+    /// Removes an item from the history, returns whether it was found
+    pub fn forget_and_save(&mut self, key: &str, item: &Item) -> anyhow::Result<bool> {
+        let mut found = false;
+        self.modify_and_save(|recent| {
+            if let Some(mode_items) = recent.items.get_mut(key) {
+                let len = mode_items.len();
+                mode_items.retain(|i| *i.item != *item);
+                found = mode_items.len() != len;
+            }
+        })?;
+        Ok(found)
+    }
+
+    /// Clears the history of a mode, or of all modes if no key is given
+    pub fn clear_and_save(&mut self, key: Option<&str>) -> anyhow::Result<()> {
+        self.modify_and_save(|recent| match key {
+            Some(key) => {
+                recent.items.remove(key);
+            }
+            None => recent.items.clear(),
+        })
+    }
+
+    /// Pins or unpins an item, an item that is not in the history yet is added to it
+    pub fn set_pinned_and_save(
+        &mut self,
+        key: &str,
+        item: Arc<Item>,
+        pinned: bool,
+    ) -> anyhow::Result<()> {
+        self.modify_and_save(|recent| {
+            let mode_items = recent.items.entry(key.to_string()).or_default();
+            match mode_items.iter_mut().find(|i| i.item == item) {
+                Some(recent_item) => recent_item.pinned = pinned,
+                None if pinned => {
+                    let mut recent_item = RecentItem::new(item);
+                    recent_item.pinned = true;
+                    mode_items.insert(0, recent_item);
+                }
+                None => {}
+            }
+        })
+    }
+
+    /// Removes the items of which the file or program no longer exists from the history of a
+    /// mode, or of all modes if no key is given, returns the number of removed items
+    pub fn prune_and_save(&mut self, key: Option<&str>) -> anyhow::Result<usize> {
+        let mut pruned = 0;
+        self.modify_and_save(|recent| pruned = recent.prune(key))?;
+        Ok(pruned)
+    }
+
+    /// Removes the unpinned items of which the file or program no longer exists
+    fn prune(&mut self, key: Option<&str>) -> usize {
+        let mut pruned = 0;
+        for (mode_key, mode_items) in &mut self.items {
+            if key.is_some_and(|key| key != mode_key) {
+                continue;
+            }
+            mode_items.retain(|i| {
+                let stale = !i.pinned && is_stale(&i.item);
+                if stale {
+                    log::info!("pruning unavailable item from history: {}", i.item);
+                    pruned += 1;
+                }
+                !stale
+            });
+        }
+        pruned
+    }
+
+    /// Applies a modification to the latest state on disk and saves it
+    /// The file is reloaded under an exclusive lock first, so changes made by other instances in
+    /// the meantime are merged instead of overwritten
+    fn modify_and_save(&mut self, modify: impl FnOnce(&mut Self)) -> anyhow::Result<()> {
+        let Some(path) = self.path.clone() else {
+            // not backed by a file, only keep the changes in memory
+            modify(self);
+            return Ok(());
+        };
+        let _lock = FileLock::exclusive(&path)?;
+        let mut latest = Self::read(&path)?;
+        modify(&mut latest);
+        latest.write(&path)?;
+        latest.path = Some(path);
+        *self = latest;
+        Ok(())
+    }
+
+    /// Writes the items to a temporary file which then replaces the file atomically, so a crash
+    /// never leaves a partially written file behind
+    fn write(&self, path: &Path) -> anyhow::Result<()> {
+        let tmp_path = path.with_extension(TMP_EXTENSION);
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(&format::encode(self)?)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    pub fn get_items(&self, key: &str) -> Vec<Arc<Item>> {
+        if let Some(items) = self.items.get(key) {
+            items.iter().map(|i| i.item.clone()).collect()
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Iterates over all recent items with the key of their mode
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &RecentItem)> {
+        self.items
+            .iter()
+            .flat_map(|(key, items)| items.iter().map(move |i| (key.as_str(), i)))
+    }
+
+    /// Returns the recent items of a mode by item
+    pub fn get_recent(&self, key: &str) -> HashMap<&Item, &RecentItem> {
+        self.items
+            .get(key)
+            .map(|items| items.iter().map(|i| (i.item.as_ref(), i)).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Whether the file or program of the item no longer exists
+/// A file of which the directory is gone as well is kept, it may be on a drive that is not mounted
+fn is_stale(item: &Item) -> bool {
+    match &item.action {
+        Action::File { path, .. } => !path.exists() && path.parent().is_some_and(Path::exists),
+        _ => !item.is_available(),
+    }
+}
+
+/// Whether reading the file failed because it is corrupt, a file of a newer version is left
+/// untouched so that version can still read it
+fn is_corrupt(e: &anyhow::Error) -> bool {
+    matches!(e.downcast_ref(), Some(DecodeError::Corrupt(_)))
+}
+
+/// An advisory lock on a separate lock file next to the given file
+/// The lock is released when it is dropped
+struct FileLock(File);
+
+impl FileLock {
+    fn shared(path: &Path) -> io::Result<Self> {
+        Self::lock(path, libc::LOCK_SH)
+    }
+
+    fn exclusive(path: &Path) -> io::Result<Self> {
+        Self::lock(path, libc::LOCK_EX)
+    }
+
+    fn lock(path: &Path, operation: libc::c_int) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path.with_extension(LOCK_EXTENSION))?;
+        // blocks until the lock is acquired
+        if unsafe { libc::flock(file.as_raw_fd(), operation) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self(file))
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        unsafe {
+            libc::flock(self.0.as_raw_fd(), libc::LOCK_UN);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 24 * 60 * 60;
+
+    fn recent_item(launches: &[u64]) -> RecentItem {
+        let mut recent_item = RecentItem::new(Item::new_selection("test".to_string()).into());
+        for &time in launches.iter().rev() {
+            recent_item.launch(time, "");
+        }
+        recent_item
+    }
+
+    #[test]
+    fn test_frecency_decay() {
+        let now = 100 * DAY;
+        let recent = recent_item(&[now]);
+        let week_old = recent_item(&[now - 7 * DAY]);
+        assert_eq!(recent.frecency(now), 1.0);
+        assert!((week_old.frecency(now) - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_frecency_count() {
+        let now = 100 * DAY;
+        let once = recent_item(&[now - DAY]);
+        let often = recent_item(&[now - DAY, now - 2 * DAY, now - 3 * DAY]);
+        assert!(often.frecency(now) > once.frecency(now));
+    }
+
+    #[test]
+    fn test_query_score() {
+        let mut recent_item = recent_item(&[]);
+        recent_item.launch(0, "fi");
+        recent_item.launch(0, "Fi ");
+        assert_eq!(recent_item.query_score("fi"), 2.0);
+        assert_eq!(recent_item.query_score("f"), 0.0);
+        assert_eq!(recent_item.query_score("fire"), 1.0);
+        assert_eq!(recent_item.query_score("th"), 0.0);
+        assert_eq!(recent_item.query_score(""), 0.0);
+    }
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "{STATE_DIR_NAME}-test-{name}-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_save_merges_concurrent_changes() {
+        let path = test_dir("merge").join(RECENT_FILE_NAME);
+        let mut first = RecentItems::load_from(path.clone()).unwrap();
+        let mut second = RecentItems::load_from(path.clone()).unwrap();
+        first
+            .insert_and_save("run", Item::new_selection("foo".to_string()).into(), "")
+            .unwrap();
+        second
+            .insert_and_save("run", Item::new_selection("bar".to_string()).into(), "")
+            .unwrap();
+        let mut items = RecentItems::load_from(path).unwrap().get_items("run");
+        items.sort_by(|a, b| a.text.cmp(&b.text));
+        assert_eq!(
+            items,
+            vec![
+                Arc::new(Item::new_selection("bar".to_string())),
+                Arc::new(Item::new_selection("foo".to_string()))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_corrupt_file() {
+        let path = test_dir("corrupt").join(RECENT_FILE_NAME);
+        fs::write(&path, [0xff; 16]).unwrap();
+        let recent = RecentItems::load_from(path.clone()).unwrap();
+        assert!(recent.get_items("run").is_empty());
+        assert!(!path.exists());
+        assert!(path.with_extension(CORRUPT_EXTENSION).exists());
+    }
+
+    fn selection(text: &str) -> Arc<Item> {
+        Arc::new(Item::new_selection(text.to_string()))
+    }
+
+    fn file(path: PathBuf) -> Arc<Item> {
+        let text = path.display().to_string();
+        Arc::new(Item::new(
+            text,
+            Action::File {
+                path,
+                is_dir: false,
+            },
+        ))
+    }
+
+    #[test]
+    fn test_forget_and_clear() {
+        let mut recent = RecentItems::default();
+        recent.insert_and_save("run", selection("foo"), "").unwrap();
+        recent.insert_and_save("run", selection("bar"), "").unwrap();
+        recent
+            .insert_and_save("apps", selection("baz"), "")
+            .unwrap();
+
+        assert!(recent.forget_and_save("run", &selection("foo")).unwrap());
+        assert!(!recent.forget_and_save("run", &selection("foo")).unwrap());
+        assert_eq!(recent.get_items("run"), vec![selection("bar")]);
+
+        recent.clear_and_save(Some("run")).unwrap();
+        assert!(recent.get_items("run").is_empty());
+        assert_eq!(recent.get_items("apps"), vec![selection("baz")]);
+        recent.clear_and_save(None).unwrap();
+        assert!(recent.get_items("apps").is_empty());
+    }
+
+    #[test]
+    fn test_pin() {
+        let mut recent = RecentItems::default();
+        recent
+            .insert_and_save("run", selection("pinned"), "")
+            .unwrap();
+        recent
+            .set_pinned_and_save("run", selection("pinned"), true)
+            .unwrap();
+        // pinning an item that is not in the history yet adds it
+        recent
+            .set_pinned_and_save("run", selection("new"), true)
+            .unwrap();
+        for _ in 0..3 {
+            recent
+                .insert_and_save("run", selection("often"), "")
+                .unwrap();
+        }
+        let items = recent.get_items("run");
+        assert_eq!(items.last(), Some(&selection("often")));
+        assert!(recent.items["run"][..2].iter().all(|i| i.pinned));
+
+        recent
+            .set_pinned_and_save("run", selection("pinned"), false)
+            .unwrap();
+        recent
+            .set_pinned_and_save("run", selection("gone"), false)
+            .unwrap();
+        assert_eq!(recent.get_recent("run").len(), 3);
+        assert!(!recent.get_recent("run")[&*selection("pinned")].pinned);
+    }
+
+    #[test]
+    fn test_prune() {
+        let dir = test_dir("prune");
+        let existing = dir.join("existing");
+        fs::write(&existing, "").unwrap();
+        let mut recent = RecentItems::default();
+        for item in [
+            file(existing.clone()),
+            file(dir.join("removed")),
+            file(dir.join("missing-dir").join("unmounted")),
+            file(dir.join("pinned")),
+        ] {
+            recent.insert("files", item, "");
+        }
+        recent
+            .set_pinned_and_save("files", file(dir.join("pinned")), true)
+            .unwrap();
+        recent.insert("run", selection("foo"), "");
+
+        assert_eq!(recent.prune_and_save(None).unwrap(), 1);
+        assert_eq!(recent.get_items("run"), vec![selection("foo")]);
+        let mut items: Vec<_> = recent.get_items("files");
+        items.sort_by(|a, b| a.text.cmp(&b.text));
+        assert_eq!(
+            items,
+            vec![
+                file(existing),
+                file(dir.join("missing-dir").join("unmounted")),
+                file(dir.join("pinned")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_prune_on_load() {
+        let dir = test_dir("prune-on-load");
+        let path = dir.join(RECENT_FILE_NAME);
+        let removed = dir.join("removed");
+        fs::write(&removed, "").unwrap();
+        let mut recent = RecentItems::load_from(path.clone()).unwrap();
+        recent
+            .insert_and_save("files", file(removed.clone()), "")
+            .unwrap();
+        fs::remove_file(&removed).unwrap();
+
+        let recent = RecentItems::load_from(path).unwrap();
+        assert!(recent.get_recent("files").is_empty());
+    }
+
+    #[test]
+    fn test_failed_save_keeps_file() {
+        let dir = test_dir("failed-save");
+        let path = dir.join(RECENT_FILE_NAME);
+        let mut recent = RecentItems::load_from(path.clone()).unwrap();
+        // the lock file can not be created without the directory
+        fs::remove_dir_all(&dir).unwrap();
+        let item = Arc::new(Item::new_selection("foo".to_string()));
+        assert!(recent.insert_and_save("run", item.clone(), "").is_err());
+        fs::create_dir_all(&dir).unwrap();
+        recent.insert_and_save("run", item.clone(), "").unwrap();
+        assert_eq!(
+            RecentItems::load_from(path).unwrap().get_items("run"),
+            vec![item]
+        );
+    }
+}
@@ -49,6 +49,12 @@ impl From<cosmic_text::Color> for Color {
     }
 }
 
+impl From<Color> for cosmic_text::Color {
+    fn from(color: Color) -> Self {
+        Self::rgba(color.red(), color.green(), color.blue(), color.alpha())
+    }
+}
+
 impl Color {
     #[inline]
     pub fn red(&self) -> u8 {
@@ -1,8 +1,20 @@
 use crate::render::DrawHandle;
 
-use super::{DynWidget, UVec2, Widget};
-use std::{cell::RefCell, rc::Rc};
+use super::{Color, DynWidget, Rect, UVec2, Widget};
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
 
+/// Width of the scrollbar, including the gap between the items and the scrollbar
+const SCROLLBAR_WIDTH: u32 = 8;
+const SCROLLBAR_GAP: u32 = 4;
+/// Minimum height of the scrollbar thumb, so it stays visible for long lists
+const MIN_THUMB_HEIGHT: u32 = 8;
+
+/// A list of items with a fixed height, of which only the visible part is stored
+/// The list keeps track of the scroll offset into the full list of items, the items it contains
+/// are the ones that are visible at that offset
 #[derive(Default)]
 pub struct List {
     items: Vec<DynWidget>,
@@ -10,6 +22,14 @@ pub struct List {
     item_height: u32,
     item_width: u32,
     max_items: usize,
+    /// Index of the first visible item in the full list
+    offset: usize,
+    /// Number of items in the full list
+    total: usize,
+    scrollbar: Option<Color>,
+    height: u32,
+    /// Position at which the list was last rendered, used for hit testing
+    position: Cell<UVec2>,
 }
 
 impl List {
@@ -32,15 +52,43 @@ impl List {
             item.layout(item_bounds);
         }
     }
+
+    fn render_scrollbar(&self, pos: UVec2, color: Color, draw_handle: &mut DrawHandle) {
+        if self.total <= self.max_items || self.height == 0 {
+            return;
+        }
+        let thumb_height = ((self.height as u64 * self.max_items as u64 / self.total as u64)
+            as u32)
+            .max(MIN_THUMB_HEIGHT)
+            .min(self.height);
+        let max_offset = self.total - self.max_items;
+        let thumb_y = ((self.height - thumb_height) as u64 * self.offset.min(max_offset) as u64
+            / max_offset as u64) as u32;
+        draw_handle.draw_rect(
+            Rect::new(
+                pos.x + self.item_width + SCROLLBAR_GAP,
+                pos.y + thumb_y,
+                SCROLLBAR_WIDTH - SCROLLBAR_GAP,
+                thumb_height,
+            ),
+            color,
+        );
+    }
 }
 
 impl Widget for List {
     fn layout(&mut self, bounds: UVec2) -> UVec2 {
         let new_max_items = (bounds.y / (self.item_height + self.spacing)) as usize;
+        let item_width = if self.scrollbar.is_some() {
+            bounds.x.saturating_sub(SCROLLBAR_WIDTH)
+        } else {
+            bounds.x
+        };
         // only relayout if nessessary
-        let relayout = bounds.x != self.item_width || new_max_items > self.max_items;
-        self.item_width = bounds.x;
+        let relayout = item_width != self.item_width || new_max_items > self.max_items;
+        self.item_width = item_width;
         self.max_items = new_max_items;
+        self.height = bounds.y;
         if relayout {
             self.relayout_items();
         }
@@ -49,10 +97,14 @@ impl Widget for List {
     }
 
     fn render(&self, pos: UVec2, draw_handle: &mut DrawHandle) {
+        self.position.set(pos);
         for (i, child) in self.items.iter().take(self.max_items).enumerate() {
             let offset = UVec2::new(0, i as u32 * (self.item_height + self.spacing));
             child.render(pos + offset, draw_handle);
         }
+        if let Some(color) = self.scrollbar {
+            self.render_scrollbar(pos, color, draw_handle);
+        }
     }
 }
 
@@ -64,6 +116,13 @@ impl DynamicList {
         Self(Rc::new(RefCell::new(List::new(item_height, spacing))))
     }
 
+    /// Shows a scrollbar with the given color when not all items fit
+    pub fn scrollbar(self, color: Color) -> Self {
+        self.0.borrow_mut().scrollbar = Some(color);
+        self
+    }
+
+    /// Replaces the visible items, which start at the current offset
     pub fn update<I, E>(&mut self, new_items: I)
     where
         I: IntoIterator<Item = E>,
@@ -76,9 +135,47 @@ impl DynamicList {
         inner.relayout_items();
     }
 
+    /// Scrolls the least amount needed to make the item at the index visible, returns the new
+    /// offset, which is the index of the first visible item
+    pub fn scroll_to(&mut self, index: usize, total: usize) -> usize {
+        let mut inner = self.0.borrow_mut();
+        let max_items = inner.max_items.max(1);
+        inner.total = total;
+        if index < inner.offset {
+            inner.offset = index;
+        } else if index >= inner.offset + max_items {
+            inner.offset = index + 1 - max_items;
+        }
+        // do not leave empty rows at the end when items were removed
+        inner.offset = inner.offset.min(total.saturating_sub(max_items));
+        inner.offset
+    }
+
     pub fn max_items(&self) -> usize {
         self.0.borrow().max_items
     }
+
+    /// Index of the first visible item
+    pub fn offset(&self) -> usize {
+        self.0.borrow().offset
+    }
+
+    /// Returns the index in the full list of the item at the point, e.g. the mouse cursor
+    /// Points in the spacing between items or on the scrollbar do not hit any item
+    pub fn item_at(&self, point: UVec2) -> Option<usize> {
+        let inner = self.0.borrow();
+        let pos = inner.position.get();
+        if point.x < pos.x || point.y < pos.y || point.x >= pos.x + inner.item_width {
+            return None;
+        }
+        let y = point.y - pos.y;
+        let row = (y / (inner.item_height + inner.spacing)) as usize;
+        let visible = inner.items.len().min(inner.max_items);
+        if row >= visible || y % (inner.item_height + inner.spacing) >= inner.item_height {
+            return None;
+        }
+        Some(inner.offset + row)
+    }
 }
 
 impl Widget for DynamicList {
@@ -90,3 +187,41 @@ impl Widget for DynamicList {
         self.0.borrow().render(pos, draw_handle);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list_with_rows(rows: u32) -> DynamicList {
+        let mut list = DynamicList::new(20, 0);
+        list.layout(UVec2::new(100, rows * 20));
+        list
+    }
+
+    #[test]
+    fn test_scroll_to() {
+        let mut list = list_with_rows(5);
+        assert_eq!(list.scroll_to(3, 100), 0);
+        assert_eq!(list.scroll_to(5, 100), 1);
+        assert_eq!(list.scroll_to(50, 100), 46);
+        assert_eq!(list.scroll_to(47, 100), 46);
+        assert_eq!(list.scroll_to(10, 100), 10);
+        assert_eq!(list.scroll_to(99, 100), 95);
+        // the list shrinks, e.g. after typing
+        assert_eq!(list.scroll_to(0, 3), 0);
+    }
+
+    #[test]
+    fn test_item_at() {
+        let mut list = DynamicList::new(20, 4);
+        list.layout(UVec2::new(100, 5 * 24));
+        list.update((0..10).map(|_| list_with_rows(1).into_dyn()));
+        list.scroll_to(6, 10);
+        assert_eq!(list.item_at(UVec2::new(10, 0)), Some(2));
+        assert_eq!(list.item_at(UVec2::new(10, 50)), Some(4));
+        // in the spacing between two items
+        assert_eq!(list.item_at(UVec2::new(10, 22)), None);
+        assert_eq!(list.item_at(UVec2::new(100, 10)), None);
+        assert_eq!(list.item_at(UVec2::new(10, 5 * 24)), None);
+    }
+}
@@ -1,12 +1,17 @@
 use super::{Color, Rect, UVec2, Widget};
-use crate::render::{BorrowedBuffer, DrawHandle};
+use crate::{
+    clipboard::Clipboard,
+    render::{BorrowedBuffer, DrawHandle},
+};
+use anyhow::Result;
 use cosmic_text::{
-    Action, Attrs, CacheKeyFlags, Edit, Family, FontFeatures, FontSystem, Metrics, Motion, Shaping,
-    Stretch, Style, SwashCache, TextDecoration, Weight, Wrap,
+    Action, Attrs, AttrsList, CacheKeyFlags, Cursor, Edit, Family, FontFeatures, FontSystem,
+    Metrics, Motion, PhysicalGlyph, Selection, Shaping, Stretch, Style, SwashCache, TextDecoration,
+    UnderlineStyle, Weight, Wrap,
 };
 use once_cell::sync::Lazy;
-use std::{cell::RefCell, collections::HashMap, rc::Rc, sync::Mutex};
-use winit::keyboard::KeyCode;
+use std::{cell::RefCell, collections::HashMap, ops::Range, rc::Rc, sync::Mutex};
+use winit::keyboard::{KeyCode, ModifiersState};
 
 static FONT_SYSTEM: Lazy<Mutex<FontSystem>> = Lazy::new(|| Mutex::new(FontSystem::new()));
 static SWASH_CACHE: Lazy<Mutex<SwashCache>> = Lazy::new(|| Mutex::new(SwashCache::new()));
@@ -14,7 +19,8 @@ static GLYPH_CACHE: Lazy<Mutex<GlyphCache>> = Lazy::new(|| Mutex::new(GlyphCache
 
 #[derive(Default)]
 struct GlyphCache {
-    cache: HashMap<cosmic_text::CacheKey, Option<CachcedGlyph>>,
+    /// Glyphs are cached per color, because the color is baked into the texture
+    cache: HashMap<(cosmic_text::CacheKey, Option<cosmic_text::Color>), Option<CachcedGlyph>>,
 }
 
 struct CachcedGlyph {
@@ -38,12 +44,92 @@ const DEFAULT_ATTRS: Attrs = Attrs {
 
 const DEFAULT_FONT_SIZE: f32 = 18.0;
 
+/// Replaces the installed fonts with the given font files, so rendering does not depend on the
+/// system, should be called before any text is created
+pub fn set_fonts(fonts: impl IntoIterator<Item = Vec<u8>>) {
+    let mut db = cosmic_text::fontdb::Database::new();
+    for font in fonts {
+        db.load_font_data(font);
+    }
+    *FONT_SYSTEM.lock().unwrap() = FontSystem::new_with_locale_and_db("en-US".to_string(), db);
+    *SWASH_CACHE.lock().unwrap() = SwashCache::new();
+    GLYPH_CACHE.lock().unwrap().cache.clear();
+}
+
+/// Style of a span of text, unset values fall back to the style of the whole text
+#[derive(Debug, Clone, Default)]
+pub struct SpanStyle {
+    pub color: Option<Color>,
+    /// Font weight, from 100 (thin) to 900 (black), where 400 is normal and 700 is bold
+    pub weight: Option<u16>,
+    pub italic: bool,
+    pub underline: bool,
+    pub font_name: Option<String>,
+}
+
+impl SpanStyle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    pub fn weight(mut self, weight: u16) -> Self {
+        self.weight = Some(weight);
+        self
+    }
+
+    pub fn bold(self) -> Self {
+        self.weight(Weight::BOLD.0)
+    }
+
+    pub fn italic(mut self) -> Self {
+        self.italic = true;
+        self
+    }
+
+    pub fn underline(mut self) -> Self {
+        self.underline = true;
+        self
+    }
+
+    pub fn font(mut self, font_name: impl Into<String>) -> Self {
+        self.font_name = Some(font_name.into());
+        self
+    }
+
+    /// Applies the style on top of the attributes
+    fn apply<'a>(&'a self, attrs: &Attrs<'a>) -> Attrs<'a> {
+        let mut attrs = attrs.clone();
+        if let Some(color) = self.color {
+            attrs.color_opt = Some(color.into());
+        }
+        if let Some(weight) = self.weight {
+            attrs.weight = Weight(weight);
+        }
+        if self.italic {
+            attrs.style = Style::Italic;
+        }
+        if self.underline {
+            attrs.text_decoration.underline = UnderlineStyle::Single;
+        }
+        if let Some(font_name) = &self.font_name {
+            attrs.family = Family::Name(font_name);
+        }
+        attrs
+    }
+}
+
 pub struct TextBuilder {
     text: String,
     size: Option<f32>,
     line_height: Option<f32>,
     font_name: Option<String>,
     bold: bool,
+    spans: Vec<(Range<usize>, SpanStyle)>,
 }
 
 impl TextBuilder {
@@ -54,6 +140,7 @@ impl TextBuilder {
             line_height: None,
             font_name: None,
             bold: false,
+            spans: Vec::new(),
         }
     }
 
@@ -77,6 +164,22 @@ impl TextBuilder {
         self
     }
 
+    /// Styles a byte range of the text, the range must lie on character boundaries
+    /// Overlapping spans are not supported, the part of a span that overlaps with a previous span
+    /// is ignored
+    pub fn span(mut self, range: Range<usize>, style: SpanStyle) -> Self {
+        self.spans.push((range, style));
+        self
+    }
+
+    /// Appends a styled span to the text
+    pub fn append(mut self, text: &str, style: SpanStyle) -> Self {
+        let start = self.text.len();
+        self.text.push_str(text);
+        self.spans.push((start..self.text.len(), style));
+        self
+    }
+
     pub fn build(self) -> Text {
         let size = self.size.unwrap_or(DEFAULT_FONT_SIZE);
         Text::new(
@@ -85,6 +188,7 @@ impl TextBuilder {
             self.line_height.unwrap_or(size),
             self.bold,
             self.font_name,
+            self.spans,
         )
     }
 }
@@ -96,7 +200,14 @@ pub struct Text {
 }
 
 impl Text {
-    fn new(text: &str, size: f32, line_height: f32, bold: bool, font_name: Option<String>) -> Self {
+    fn new(
+        text: &str,
+        size: f32,
+        line_height: f32,
+        bold: bool,
+        font_name: Option<String>,
+        mut spans: Vec<(Range<usize>, SpanStyle)>,
+    ) -> Self {
         let mut font_system = FONT_SYSTEM.lock().unwrap();
 
         let mut attrs = DEFAULT_ATTRS;
@@ -105,11 +216,26 @@ impl Text {
         }
         attrs.weight = if bold { Weight::BOLD } else { Weight::NORMAL };
 
+        // split the text into segments that are either styled by a span or use the default style
+        spans.sort_by_key(|(range, _)| range.start);
+        let mut segments = Vec::with_capacity(spans.len() * 2 + 1);
+        let mut pos = 0;
+        for (range, style) in &spans {
+            let range = range.start.max(pos)..range.end.min(text.len());
+            if range.is_empty() {
+                continue;
+            }
+            segments.push((&text[pos..range.start], attrs.clone()));
+            segments.push((&text[range.clone()], style.apply(&attrs)));
+            pos = range.end;
+        }
+        segments.push((&text[pos..], attrs.clone()));
+
         let mut buffer =
             cosmic_text::Buffer::new(&mut font_system, Metrics::new(size, line_height));
         buffer.set_wrap(Wrap::None);
         // use advanced shaping to get all font features, like emojis and ligatures
-        buffer.set_text(text, &attrs, Shaping::Advanced, None);
+        buffer.set_rich_text(segments, &attrs, Shaping::Advanced, None);
         buffer.shape_until_scroll(&mut font_system, false);
 
         let (width, height) = buffer.size();
@@ -174,7 +300,7 @@ impl Widget for Text {
 
                 if let Some(glyph) = glyph_cache
                     .cache
-                    .entry(physical_glyph.cache_key)
+                    .entry((physical_glyph.cache_key, glyph.color_opt))
                     .or_insert_with(|| {
                         // For now, draw the glyph with a white color
                         // TODO: transparency
@@ -208,10 +334,78 @@ impl Widget for Text {
                     );
                 }
             }
+            cosmic_text::render_decoration(
+                &mut DecorationRenderer { pos, draw_handle },
+                &run,
+                cosmic_text::Color::rgb(0xFF, 0xFF, 0xFF),
+            );
+        }
+    }
+}
+
+/// A [`Text`] that can be replaced after the UI is built
+#[derive(Clone)]
+pub struct DynamicText(Rc<RefCell<DynamicTextInner>>);
+
+struct DynamicTextInner {
+    text: Text,
+    /// The bounds of the last layout, a new text is laid out within the same bounds
+    bounds: Option<UVec2>,
+}
+
+impl DynamicText {
+    pub fn new(text: Text) -> Self {
+        Self(Rc::new(RefCell::new(DynamicTextInner {
+            text,
+            bounds: None,
+        })))
+    }
+
+    /// Replaces the text, the parent is not laid out again so the new text should have the same
+    /// height as the old one
+    pub fn set(&self, text: Text) {
+        let mut inner = self.0.borrow_mut();
+        inner.text = text;
+        if let Some(bounds) = inner.bounds {
+            inner.text.layout(bounds);
         }
     }
 }
 
+impl Widget for DynamicText {
+    fn layout(&mut self, bounds: UVec2) -> UVec2 {
+        let mut inner = self.0.borrow_mut();
+        inner.bounds = Some(bounds);
+        inner.text.layout(bounds)
+    }
+
+    fn render(&self, pos: UVec2, draw_handle: &mut DrawHandle) {
+        self.0.borrow().text.render(pos, draw_handle);
+    }
+}
+
+/// Draws the text decorations (e.g. underlines) of a layout run, glyphs are drawn separately
+struct DecorationRenderer<'a> {
+    pos: UVec2,
+    draw_handle: &'a mut DrawHandle,
+}
+
+impl cosmic_text::Renderer for DecorationRenderer<'_> {
+    fn rectangle(&mut self, x: i32, y: i32, w: u32, h: u32, color: cosmic_text::Color) {
+        self.draw_handle.draw_rect(
+            Rect::new(
+                (self.pos.x as i32 + x).max(0) as u32,
+                (self.pos.y as i32 + y).max(0) as u32,
+                w,
+                h,
+            ),
+            Color::from(color).premultiply(),
+        );
+    }
+
+    fn glyph(&mut self, _physical_glyph: PhysicalGlyph, _color: cosmic_text::Color) {}
+}
+
 fn convert_image(image: &cosmic_text::SwashImage, color: Color) -> Option<Vec<u8>> {
     let glyph_size = image.placement.width as usize * image.placement.height as usize;
 
@@ -260,6 +454,36 @@ fn convert_image(image: &cosmic_text::SwashImage, color: Color) -> Option<Vec<u8
 #[derive(Clone)]
 pub struct Editor {
     inner: Rc<RefCell<cosmic_text::Editor<'static>>>,
+    state: Rc<RefCell<EditState>>,
+}
+
+/// The text and cursor position before an edit, used for undo and redo
+#[derive(Debug, Clone)]
+struct EditSnapshot {
+    text: String,
+    cursor: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EditKind {
+    /// Inserted text, consecutive insertions are undone at once
+    Insert,
+    Delete,
+}
+
+#[derive(Default)]
+struct EditState {
+    undo: Vec<EditSnapshot>,
+    redo: Vec<EditSnapshot>,
+    /// The kind of the last edit, `None` if the cursor moved since then
+    last_edit: Option<EditKind>,
+    /// The last killed text, which can be yanked back
+    kill_buffer: String,
+    /// Byte range of the text that is being composed by an input method, it is shown in the
+    /// buffer but not part of the text yet
+    preedit: Option<Range<usize>>,
+    /// Position at which the editor was last rendered
+    position: UVec2,
 }
 
 impl Editor {
@@ -281,29 +505,259 @@ impl Editor {
         });
         Self {
             inner: Rc::new(RefCell::new(editor)),
+            state: Rc::new(RefCell::new(EditState::default())),
         }
     }
 
     pub fn text(&self) -> String {
+        let mut text = self
+            .inner
+            .borrow()
+            .with_buffer(|buf| buf.lines[0].text().to_string());
+        if let Some(preedit) = &self.state.borrow().preedit {
+            text.replace_range(preedit.clone(), "");
+        }
+        text
+    }
+
+    /// Byte index of the cursor in the text
+    pub fn cursor(&self) -> usize {
+        self.inner.borrow().cursor().index
+    }
+
+    /// The selected text, if any
+    pub fn selection(&self) -> Option<String> {
         self.inner
             .borrow()
-            .with_buffer(|buf| buf.lines[0].text().to_string())
+            .copy_selection()
+            .filter(|text| !text.is_empty())
+    }
+
+    /// Replaces the text and moves the cursor to its end, the edits cannot be undone anymore
+    pub fn set_text(&mut self, text: &str) {
+        self.clear_preedit();
+        self.restore(&EditSnapshot {
+            text: text.to_string(),
+            cursor: text.len(),
+        });
+        let mut state = self.state.borrow_mut();
+        state.undo.clear();
+        state.redo.clear();
+    }
+
+    /// Shows the text that is being composed by an input method at the cursor, underlined
+    /// The cursor is placed at the start of the byte range `cursor` of the text, or at its end
+    pub fn set_preedit(&mut self, text: &str, cursor: Option<(usize, usize)>) {
+        self.clear_preedit();
+        if text.is_empty() {
+            return;
+        }
+        let mut font_system = FONT_SYSTEM.lock().unwrap();
+        let mut editor = self.inner.borrow_mut();
+        editor.set_selection(Selection::None);
+        let attrs = editor.with_buffer(|buf| {
+            let mut attrs = buf.lines[0].attrs_list().defaults();
+            attrs.text_decoration.underline = UnderlineStyle::Single;
+            AttrsList::new(&attrs)
+        });
+        let start = editor.cursor();
+        let end = editor.insert_at(start, text, Some(attrs));
+        let offset = cursor.map_or(text.len(), |(start, _)| start.min(text.len()));
+        editor.set_cursor(Cursor::new(0, start.index + offset));
+        editor.shape_as_needed(&mut font_system, false);
+        self.state.borrow_mut().preedit = Some(start.index..end.index);
+    }
+
+    /// Removes the text that is being composed, returns whether there was any
+    pub fn clear_preedit(&mut self) -> bool {
+        let Some(preedit) = self.state.borrow_mut().preedit.take() else {
+            return false;
+        };
+        let mut font_system = FONT_SYSTEM.lock().unwrap();
+        let mut editor = self.inner.borrow_mut();
+        editor.delete_range(Cursor::new(0, preedit.start), Cursor::new(0, preedit.end));
+        editor.set_cursor(Cursor::new(0, preedit.start));
+        editor.shape_as_needed(&mut font_system, false);
+        true
+    }
+
+    /// Inserts the text composed by an input method, replacing the preedit text
+    pub fn commit(&mut self, text: &str) {
+        self.clear_preedit();
+        self.insert(text);
+    }
+
+    /// The area of the cursor on the screen, the input method shows its candidates next to it
+    pub fn cursor_area(&self) -> Option<Rect> {
+        let editor = self.inner.borrow();
+        let (x, y) = editor.cursor_position()?;
+        let line_height = editor.with_buffer(|buf| buf.metrics().line_height);
+        let position = self.state.borrow().position;
+        Some(Rect::new(
+            position.x + x.max(0) as u32,
+            position.y + y.max(0) as u32,
+            1,
+            line_height as u32,
+        ))
     }
 
-    pub fn handle_key(&mut self, key: KeyCode) -> bool {
+    /// Handles the editing keys that are not bound to an action, holding shift selects text
+    pub fn handle_key(&mut self, key: KeyCode, modifiers: ModifiersState) -> bool {
+        let select = modifiers.shift_key();
+        let word = modifiers.control_key();
         match key {
-            KeyCode::Backspace => self.perform_action(Action::Backspace),
-            KeyCode::Delete => self.perform_action(Action::Delete),
-            KeyCode::ArrowLeft => self.perform_action(Action::Motion(Motion::Left)),
-            KeyCode::ArrowRight => self.perform_action(Action::Motion(Motion::Right)),
-            KeyCode::Home => self.perform_action(Action::Motion(Motion::Home)),
-            KeyCode::End => self.perform_action(Action::Motion(Motion::End)),
+            KeyCode::Backspace => self.edit(EditKind::Delete, |editor| {
+                editor.perform_action(Action::Backspace)
+            }),
+            KeyCode::Delete => self.edit(EditKind::Delete, |editor| {
+                editor.perform_action(Action::Delete)
+            }),
+            KeyCode::ArrowLeft if word => self.move_cursor(Motion::PreviousWord, select),
+            KeyCode::ArrowRight if word => self.move_cursor(Motion::NextWord, select),
+            KeyCode::ArrowLeft => self.move_cursor(Motion::Left, select),
+            KeyCode::ArrowRight => self.move_cursor(Motion::Right, select),
+            KeyCode::Home => self.move_cursor(Motion::Home, select),
+            KeyCode::End => self.move_cursor(Motion::End, select),
             _ => return false,
         }
         true
     }
 
-    pub fn perform_action(&mut self, action: cosmic_text::Action) {
+    /// Inserts text at the cursor, replacing the selection
+    /// Control characters are ignored, they are sent along with some key combinations
+    pub fn insert(&mut self, text: &str) -> bool {
+        let text: String = text.chars().filter(|c| !c.is_control()).collect();
+        if text.is_empty() {
+            return false;
+        }
+        self.edit(EditKind::Insert, |editor| {
+            let mut inner = editor.inner.borrow_mut();
+            inner.insert_string(&text, None);
+            inner.shape_as_needed(&mut FONT_SYSTEM.lock().unwrap(), false);
+        });
+        true
+    }
+
+    /// Inserts the text of the clipboard, lines are joined with spaces
+    pub fn paste(&mut self, clipboard: &mut dyn Clipboard) -> Result<()> {
+        let text = clipboard.get_text()?;
+        self.insert(&text.lines().collect::<Vec<_>>().join(" "));
+        Ok(())
+    }
+
+    /// Moves the cursor, extending the selection if `select` is set and removing it otherwise
+    pub fn move_cursor(&mut self, motion: Motion, select: bool) {
+        self.clear_preedit();
+        {
+            let mut editor = self.inner.borrow_mut();
+            if !select {
+                editor.set_selection(Selection::None);
+            } else if editor.selection() == Selection::None {
+                let cursor = editor.cursor();
+                editor.set_selection(Selection::Normal(cursor));
+            }
+        }
+        self.perform_action(Action::Motion(motion));
+        self.state.borrow_mut().last_edit = None;
+    }
+
+    /// Deletes the word before the cursor, or the selection if there is one
+    pub fn delete_word(&mut self) {
+        self.kill(Motion::PreviousWord);
+    }
+
+    /// Deletes the text before the cursor, or the selection if there is one
+    pub fn kill_line(&mut self) {
+        self.kill(Motion::Home);
+    }
+
+    /// Deletes the selection, or the text up to the position the motion moves to if nothing is
+    /// selected, the deleted text can be yanked
+    fn kill(&mut self, motion: Motion) {
+        self.clear_preedit();
+        if self.selection().is_none() {
+            {
+                let mut editor = self.inner.borrow_mut();
+                let cursor = editor.cursor();
+                editor.set_selection(Selection::Normal(cursor));
+            }
+            // the motion selects the text, which is then deleted
+            self.perform_action(Action::Motion(motion));
+        }
+        if let Some(killed) = self.selection() {
+            self.state.borrow_mut().kill_buffer = killed;
+        }
+        self.edit(EditKind::Delete, |editor| {
+            editor.perform_action(Action::Backspace)
+        });
+    }
+
+    /// Inserts the last killed text
+    pub fn yank(&mut self) {
+        let killed = self.state.borrow().kill_buffer.clone();
+        self.insert(&killed);
+        // a yank is undone on its own
+        self.state.borrow_mut().last_edit = None;
+    }
+
+    pub fn undo(&mut self) {
+        self.clear_preedit();
+        let snapshot = self.state.borrow_mut().undo.pop();
+        if let Some(snapshot) = snapshot {
+            let current = self.snapshot();
+            self.restore(&snapshot);
+            self.state.borrow_mut().redo.push(current);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        self.clear_preedit();
+        let snapshot = self.state.borrow_mut().redo.pop();
+        if let Some(snapshot) = snapshot {
+            let current = self.snapshot();
+            self.restore(&snapshot);
+            self.state.borrow_mut().undo.push(current);
+        }
+    }
+
+    /// Performs an edit and records the previous state for undo, if the text changed
+    fn edit(&mut self, kind: EditKind, f: impl FnOnce(&mut Self)) {
+        self.clear_preedit();
+        let before = self.snapshot();
+        f(self);
+        if self.text() == before.text {
+            return;
+        }
+        let mut state = self.state.borrow_mut();
+        if kind != EditKind::Insert || state.last_edit != Some(EditKind::Insert) {
+            state.undo.push(before);
+        }
+        state.redo.clear();
+        state.last_edit = Some(kind);
+    }
+
+    fn snapshot(&self) -> EditSnapshot {
+        EditSnapshot {
+            text: self.text(),
+            cursor: self.cursor(),
+        }
+    }
+
+    fn restore(&mut self, snapshot: &EditSnapshot) {
+        let mut font_system = FONT_SYSTEM.lock().unwrap();
+        let mut editor = self.inner.borrow_mut();
+        let end = editor.with_buffer(|buf| buf.lines[0].text().len());
+        // replace all text, so the attributes of the line are kept
+        editor.set_selection(Selection::Normal(Cursor::new(0, 0)));
+        editor.set_cursor(Cursor::new(0, end));
+        editor.delete_selection();
+        editor.insert_string(&snapshot.text, None);
+        editor.set_cursor(Cursor::new(0, snapshot.cursor));
+        editor.shape_as_needed(&mut font_system, false);
+        self.state.borrow_mut().last_edit = None;
+    }
+
+    fn perform_action(&mut self, action: cosmic_text::Action) {
         log::debug!("edit action: {:?}", action);
         let mut font_system = FONT_SYSTEM.lock().unwrap();
         let mut editor = self.inner.borrow_mut();
@@ -350,6 +804,7 @@ impl Widget for TextEditor {
     }
 
     fn render(&self, pos: UVec2, draw_handle: &mut DrawHandle) {
+        self.editor.state.borrow_mut().position = pos;
         let mut font_system = FONT_SYSTEM.lock().unwrap();
         let mut swash_cache = SWASH_CACHE.lock().unwrap();
         let mut editor = self.editor.inner.borrow_mut();
@@ -359,7 +814,7 @@ impl Widget for TextEditor {
                 &mut swash_cache,
                 cosmic_text::Color::rgb(0xFF, 0xFF, 0xFF),
                 cosmic_text::Color::rgb(0xFF, 0xFF, 0xFF),
-                cosmic_text::Color::rgb(0xAA, 0xAA, 0xFF),
+                cosmic_text::Color::rgba(0xAA, 0xAA, 0xFF, 0x60),
                 cosmic_text::Color::rgb(0xFF, 0xFF, 0xFF),
                 |x, y, w, h, color| {
                     draw_handle.draw_rect(
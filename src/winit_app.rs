@@ -3,10 +3,13 @@ use crate::{
     render::{CpuRenderer, Renderer},
     ui::UVec2,
 };
-use std::{sync::Arc, time::Instant};
+use std::{
+    sync::{Arc, mpsc},
+    time::Instant,
+};
 use winit::{
     application::ApplicationHandler,
-    dpi::PhysicalSize,
+    dpi::{PhysicalPosition, PhysicalSize},
     event::WindowEvent,
     event_loop::{ActiveEventLoop, ControlFlow, EventLoop, EventLoopProxy},
     platform::wayland::WindowAttributesExtWayland,
@@ -34,12 +37,36 @@ pub enum UserEvent {
 
 #[derive(Debug, Clone)]
 pub struct EventHandle {
-    proxy: EventLoopProxy<UserEvent>,
+    sender: EventSender,
+}
+
+#[derive(Debug, Clone)]
+enum EventSender {
+    EventLoop(EventLoopProxy<UserEvent>),
+    Channel(mpsc::Sender<UserEvent>),
 }
 
 impl EventHandle {
+    /// Creates an event handle that is not connected to an event loop, events are received on the
+    /// returned channel instead
+    pub fn channel() -> (Self, mpsc::Receiver<UserEvent>) {
+        let (tx, rx) = mpsc::channel();
+        (
+            Self {
+                sender: EventSender::Channel(tx),
+            },
+            rx,
+        )
+    }
+
     pub fn send_update(&self) {
-        if let Err(e) = self.proxy.send_event(UserEvent::Update) {
+        let res = match &self.sender {
+            EventSender::EventLoop(proxy) => proxy
+                .send_event(UserEvent::Update)
+                .map_err(|e| e.to_string()),
+            EventSender::Channel(tx) => tx.send(UserEvent::Update).map_err(|e| e.to_string()),
+        };
+        if let Err(e) = res {
             log::error!("failed to send update event: {e}");
         }
     }
@@ -58,7 +85,9 @@ impl WinitApp {
         let event_loop = EventLoop::<UserEvent>::with_user_event().build().unwrap();
         event_loop.set_control_flow(ControlFlow::Wait);
         let proxy = event_loop.create_proxy();
-        self.launcher.run(EventHandle { proxy });
+        self.launcher.run(EventHandle {
+            sender: EventSender::EventLoop(proxy),
+        });
         event_loop.run_app(&mut self).unwrap();
     }
 }
@@ -73,6 +102,8 @@ impl ApplicationHandler<UserEvent> for WinitApp {
             .with_inner_size(PhysicalSize::new(1300, 700))
             .with_name(APP_NAME, APP_NAME);
         let window = Arc::new(event_loop.create_window(attributes).unwrap());
+        // receive text from input methods and compose keys
+        window.set_ime_allowed(true);
         self.window = AppState::Running {
             window: window.clone(),
             renderer: Box::new(CpuRenderer::new(window)),
@@ -105,13 +136,47 @@ impl ApplicationHandler<UserEvent> for WinitApp {
                     let time = Instant::now();
                     renderer.render(self.launcher.root());
                     log::info!("rendered in {:?}", time.elapsed());
-                }
-                WindowEvent::KeyboardInput { event, .. } => {
-                    if self.launcher.key_input(event) {
-                        self.launcher.update();
-                        window.request_redraw();
+                    // the input method shows its candidates next to the cursor
+                    if let Some(area) = self.launcher.ime_cursor_area() {
+                        window.set_ime_cursor_area(
+                            PhysicalPosition::new(area.pos.x, area.pos.y),
+                            PhysicalSize::new(area.size.x, area.size.y),
+                        );
                     }
                 }
+                WindowEvent::ModifiersChanged(modifiers) => {
+                    self.launcher.modifiers_changed(modifiers.state());
+                }
+                WindowEvent::KeyboardInput { event, .. } if self.launcher.key_input(&event) => {
+                    self.launcher.update();
+                    window.request_redraw();
+                }
+                WindowEvent::Ime(ime) if self.launcher.ime_input(&ime) => {
+                    self.launcher.update();
+                    window.request_redraw();
+                }
+                WindowEvent::CursorMoved { position, .. }
+                    if self
+                        .launcher
+                        .cursor_moved(UVec2::new(position.x as u32, position.y as u32)) =>
+                {
+                    self.launcher.rebuild_list();
+                    window.request_redraw();
+                }
+                WindowEvent::CursorLeft { .. } if self.launcher.cursor_left() => {
+                    self.launcher.rebuild_list();
+                    window.request_redraw();
+                }
+                WindowEvent::MouseInput { state, button, .. }
+                    if self.launcher.mouse_input(state, button) =>
+                {
+                    self.launcher.update();
+                    window.request_redraw();
+                }
+                WindowEvent::MouseWheel { delta, .. } if self.launcher.mouse_wheel(delta) => {
+                    self.launcher.update();
+                    window.request_redraw();
+                }
                 _ => {}
             }
         }
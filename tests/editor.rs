@@ -0,0 +1,96 @@
+use launchr::{
+    clipboard::{Clipboard, FakeClipboard},
+    ui::Editor,
+};
+use winit::keyboard::{KeyCode, ModifiersState};
+
+fn editor_with(text: &str) -> Editor {
+    let mut editor = Editor::new(None);
+    editor.insert(text);
+    editor
+}
+
+#[test]
+fn test_kill_and_yank() {
+    let mut editor = editor_with("firefox private window");
+    editor.delete_word();
+    assert_eq!(editor.text(), "firefox private ");
+    editor.kill_line();
+    assert_eq!(editor.text(), "");
+    editor.yank();
+    assert_eq!(editor.text(), "firefox private ");
+}
+
+#[test]
+fn test_kill_selection() {
+    let mut editor = editor_with("hello world");
+    for _ in 0..5 {
+        editor.handle_key(KeyCode::ArrowLeft, ModifiersState::SHIFT);
+    }
+    editor.kill_line();
+    assert_eq!(editor.text(), "hello ");
+    editor.yank();
+    assert_eq!(editor.text(), "hello world");
+}
+
+#[test]
+fn test_undo_redo() {
+    let mut editor = editor_with("fire");
+    editor.insert("fox");
+    editor.handle_key(KeyCode::Backspace, ModifiersState::empty());
+    assert_eq!(editor.text(), "firefo");
+    editor.undo();
+    assert_eq!(editor.text(), "firefox");
+    // consecutive insertions are undone at once
+    editor.undo();
+    assert_eq!(editor.text(), "");
+    editor.redo();
+    editor.redo();
+    assert_eq!(editor.text(), "firefo");
+}
+
+#[test]
+fn test_select_and_replace() {
+    let mut editor = editor_with("hello world");
+    for _ in 0..5 {
+        editor.handle_key(KeyCode::ArrowLeft, ModifiersState::SHIFT);
+    }
+    assert_eq!(editor.selection().as_deref(), Some("world"));
+    editor.insert("🦀 crab");
+    assert_eq!(editor.text(), "hello 🦀 crab");
+    assert_eq!(editor.cursor(), "hello 🦀 crab".len());
+}
+
+#[test]
+fn test_paste() {
+    let mut clipboard = FakeClipboard::default();
+    clipboard.set_text("two\nlines").unwrap();
+    let mut editor = editor_with("paste ");
+    editor.paste(&mut clipboard).unwrap();
+    assert_eq!(editor.text(), "paste two lines");
+}
+
+#[test]
+fn test_preedit_and_commit() {
+    let mut editor = editor_with("open ");
+    editor.set_preedit("にほ", Some((6, 6)));
+    // the text being composed is not part of the input yet
+    assert_eq!(editor.text(), "open ");
+    editor.set_preedit("日本", None);
+    editor.commit("日本");
+    assert_eq!(editor.text(), "open 日本");
+    assert_eq!(editor.cursor(), "open 日本".len());
+    editor.set_preedit("ご", None);
+    assert!(editor.clear_preedit());
+    assert_eq!(editor.text(), "open 日本");
+}
+
+#[test]
+fn test_set_text() {
+    let mut editor = editor_with("apps query");
+    editor.set_text("files query");
+    assert_eq!(editor.cursor(), "files query".len());
+    // the history of the previous text is gone
+    editor.undo();
+    assert_eq!(editor.text(), "files query");
+}
@@ -0,0 +1,192 @@
+use launchr::{
+    calc,
+    config::Config,
+    headless::wait_until_loaded,
+    item::Action,
+    launcher::{Match, PAGE_SIZE, find_matches, mode_matching},
+    mode::{CalcMode, CombiMode, CustomMode, DmenuMode, MatchKind, Matching, Mode, Status},
+    recent::RecentItems,
+    winit_app::EventHandle,
+};
+
+/// Runs the mode and returns its matches for the query once all items are loaded and matched
+fn loaded_matches(mode: &mut dyn Mode, query: &str, matching: Matching) -> Vec<Match> {
+    let (event_handle, events) = EventHandle::channel();
+    mode.run(event_handle);
+    wait_until_loaded(mode, query, matching, &events);
+    let recent = RecentItems::default();
+    find_matches(mode, &recent, query, matching, PAGE_SIZE)
+}
+
+fn dmenu_matches(input: &str, query: &str, matching: Matching) -> Vec<String> {
+    let mut mode = DmenuMode::new(None, input.to_string());
+    loaded_matches(&mut mode, query, matching)
+        .into_iter()
+        .map(|m| m.item.text.clone())
+        .collect()
+}
+
+#[test]
+fn test_find_matches_without_window() {
+    let matches = dmenu_matches("firefox\nfiles\nthunderbird\n", "fire", Matching::default());
+    assert_eq!(matches, vec!["firefox"]);
+}
+
+#[test]
+fn test_find_matches_with_prefix_matching() {
+    let matching = Matching {
+        kind: MatchKind::Prefix,
+        ..Default::default()
+    };
+    let matches = dmenu_matches("firefox\nfiles\nthunderbird\n", "fi", matching);
+    assert_eq!(matches, vec!["files", "firefox"]);
+}
+
+#[test]
+fn test_find_matches_in_large_input() {
+    let input: String = (0..100_000).map(|i| format!("item {i}\n")).collect();
+    let matches = dmenu_matches(&input, "item 99999", Matching::default());
+    assert_eq!(matches.first().map(String::as_str), Some("item 99999"));
+}
+
+#[test]
+fn test_regex_matches_are_sorted_before_the_limit() {
+    let mut input: String = (0..PAGE_SIZE * 2)
+        .map(|i| format!("item {i} fox\n"))
+        .collect();
+    input.push_str("fox\n");
+    let matching = Matching {
+        kind: MatchKind::Regex,
+        ..Default::default()
+    };
+    let matches = dmenu_matches(&input, "fox", matching);
+    assert_eq!(matches.first().map(String::as_str), Some("fox"));
+}
+
+#[test]
+fn test_status_after_loading() {
+    let mut mode = DmenuMode::new(None, "firefox\nfiles\nthunderbird\n".to_string());
+    let (event_handle, events) = EventHandle::channel();
+    mode.run(event_handle);
+    wait_until_loaded(&mut mode, "fi", Matching::default(), &events);
+    assert_eq!(
+        mode.status(),
+        Status {
+            matched: 2,
+            total: 3,
+            loading: false
+        }
+    );
+}
+
+#[test]
+fn test_status_counts_filtered_matches() {
+    let mut mode = DmenuMode::new(None, "firefox\nbonfire\nfirebird\n".to_string());
+    let (event_handle, events) = EventHandle::channel();
+    mode.run(event_handle);
+    let matching = Matching {
+        kind: MatchKind::WordBoundary,
+        ..Default::default()
+    };
+    wait_until_loaded(&mut mode, "fire", matching, &events);
+    assert_eq!(mode.status().matched, 2);
+}
+
+#[test]
+fn test_status_after_loading_large_input() {
+    let input: String = (0..100_000).map(|i| format!("item {i}\n")).collect();
+    let mut mode = DmenuMode::new(None, input);
+    let (event_handle, events) = EventHandle::channel();
+    mode.run(event_handle);
+    wait_until_loaded(&mut mode, "", Matching::default(), &events);
+    assert_eq!(
+        mode.status(),
+        Status {
+            matched: 100_000,
+            total: 100_000,
+            loading: false
+        }
+    );
+}
+
+#[test]
+fn test_combi_tags_and_narrows_sources() {
+    let matches = |query: &str| -> Vec<(String, String)> {
+        let mut mode = CombiMode::new(vec![
+            (
+                "fruit".to_string(),
+                Box::new(DmenuMode::new(None, "apple\nbanana\n".to_string())),
+            ),
+            (
+                "brands".to_string(),
+                Box::new(DmenuMode::new(None, "apple inc\n".to_string())),
+            ),
+        ]);
+        loaded_matches(&mut mode, query, Matching::default())
+            .into_iter()
+            .map(|m| (m.item.text.clone(), m.source.unwrap().to_string()))
+            .collect()
+    };
+    assert_eq!(
+        matches("apple"),
+        vec![
+            ("apple".to_string(), "fruit".to_string()),
+            ("apple inc".to_string(), "brands".to_string())
+        ]
+    );
+    assert_eq!(
+        matches("!b apple"),
+        vec![("apple inc".to_string(), "brands".to_string())]
+    );
+}
+
+#[test]
+fn test_custom_mode_from_config() {
+    let config: Config = toml::from_str(
+        r#"
+        [[mode]]
+        name = "colors"
+        command = "printf 'red\ngreen\nblue\n'"
+        action = "echo {}"
+        matching = "prefix"
+        "#,
+    )
+    .unwrap();
+    let mut mode = CustomMode::new(config.modes[0].clone());
+    let matching = mode_matching(&mode, config.matching);
+    assert_eq!(matching.kind, MatchKind::Prefix);
+    let matches = loaded_matches(&mut mode, "g", matching);
+    assert_eq!(matches.len(), 1);
+    assert_eq!(
+        matches[0].item.action,
+        Action::Shell {
+            command: "echo 'green'".to_string()
+        }
+    );
+    assert_eq!(mode.status().total, 3);
+}
+
+#[test]
+fn test_calc_mode_and_inline_results() {
+    let matches = loaded_matches(&mut CalcMode::new(), "12 km in miles", Matching::default());
+    assert_eq!(matches[0].item.text, "7.4564543068 mi");
+    assert_eq!(
+        matches[0].item.action,
+        Action::Copy {
+            text: "7.4564543068 mi".to_string()
+        }
+    );
+
+    let fruit = || {
+        CombiMode::new(vec![(
+            "fruit".to_string(),
+            Box::new(DmenuMode::new(None, "apple\n".to_string())),
+        )])
+    };
+    let matches = loaded_matches(&mut fruit(), "2^10 * 3", Matching::default());
+    assert_eq!(matches[0].item.text, "3072");
+    assert_eq!(matches[0].source.as_deref(), Some(calc::INLINE_SOURCE));
+    // a single number is not a calculation
+    let matches = loaded_matches(&mut fruit(), "42", Matching::default());
+    assert!(matches.is_empty());
+}
@@ -0,0 +1,79 @@
+use launchr::{
+    headless::wait_until_loaded,
+    item::{Action, Item},
+    launcher::{PAGE_SIZE, find_matches},
+    mode::{Matching, Mode, PluginConfig, PluginMode},
+    recent::RecentItems,
+    winit_app::EventHandle,
+};
+
+fn plugin_with_history(cache_key: Option<&str>) -> PluginMode {
+    PluginMode::new(PluginConfig {
+        name: "words".to_string(),
+        command: format!("python3 {}/examples/plugin.py", env!("CARGO_MANIFEST_DIR")),
+        cache_key: cache_key.map(str::to_string),
+    })
+}
+
+fn reference_plugin() -> PluginMode {
+    plugin_with_history(None)
+}
+
+fn texts(mode: &mut PluginMode, input: &str) -> Vec<String> {
+    mode.update(input, Matching::default(), usize::MAX)
+        .into_iter()
+        .map(|scored| scored.item.text.clone())
+        .collect()
+}
+
+#[test]
+fn test_streamed_items() {
+    let mut mode = reference_plugin();
+    let (event_handle, events) = EventHandle::channel();
+    mode.run(event_handle);
+    wait_until_loaded(&mut mode, "an", Matching::default(), &events);
+    // the first two items are streamed, the last one is part of the response
+    assert_eq!(texts(&mut mode, "an"), vec!["banana", "mango", "orange"]);
+    let matches = mode.update("an", Matching::default(), 1);
+    assert_eq!(
+        matches[0].item.action,
+        Action::Shell {
+            command: "echo banana".to_string()
+        }
+    );
+}
+
+#[test]
+fn test_stale_queries_are_replaced() {
+    let mut mode = reference_plugin();
+    let (event_handle, events) = EventHandle::channel();
+    mode.run(event_handle);
+    for input in ["p", "pi", "pin"] {
+        mode.update(input, Matching::default(), 0);
+    }
+    wait_until_loaded(&mut mode, "pine", Matching::default(), &events);
+    assert_eq!(texts(&mut mode, "pine"), vec!["pineapple"]);
+    assert!(!mode.status().loading);
+}
+
+#[test]
+fn test_history_keeps_the_order_of_the_plugin() {
+    let mut mode = plugin_with_history(Some("words"));
+    let (event_handle, events) = EventHandle::channel();
+    mode.run(event_handle);
+    wait_until_loaded(&mut mode, "an", Matching::default(), &events);
+    let mut recent = RecentItems::default();
+    let orange = Item::new(
+        "orange".to_string(),
+        Action::Shell {
+            command: "echo orange".to_string(),
+        },
+    );
+    recent
+        .insert_and_save("words", orange.into(), "an")
+        .unwrap();
+    let matches = find_matches(&mut mode, &recent, "an", Matching::default(), PAGE_SIZE);
+    let texts: Vec<&str> = matches.iter().map(|m| m.item.text.as_str()).collect();
+    assert_eq!(texts, vec!["banana", "mango", "orange"]);
+    assert!(matches[2].recent);
+}
@@ -0,0 +1,101 @@
+//! Snapshot tests of the rendered text
+//!
+//! These live in their own test binary because `set_fonts` replaces the global font system, which
+//! would make the text tests in the same binary render with the fixture fonts only
+
+use launchr::{
+    render::{DrawHandle, OnwedBuffer},
+    ui::*,
+};
+use std::{fs, path::Path};
+
+/// The fonts in `tests/fixtures/fonts`, so the snapshots do not depend on the installed fonts
+const FONTS: &[&str] = &[
+    "DejaVuSansMono.ttf",
+    "DejaVuSansMono-Bold.ttf",
+    "DejaVuSerif.ttf",
+];
+
+fn fixture_path(path: &str) -> std::path::PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join(path)
+}
+
+/// Converts the BGRA render buffer into an image
+fn to_image(bytes: &[u8], width: u32, height: u32) -> image::RgbaImage {
+    image::ImageBuffer::from_fn(width, height, |x, y| {
+        let i = (x + y * width) as usize * 4;
+        let [b, g, r, a] = bytes[i..i + 4] else {
+            unreachable!()
+        };
+        image::Rgba([r, g, b, a])
+    })
+}
+
+/// Compares the image with the snapshot in `tests/snapshots`
+/// Set `UPDATE_SNAPSHOTS=1` to create or overwrite the snapshot
+fn assert_snapshot(name: &str, image: &image::RgbaImage) {
+    let path = fixture_path("snapshots").join(format!("{name}.png"));
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        image.save(&path).unwrap();
+        return;
+    }
+    assert!(
+        path.exists(),
+        "snapshot {name} does not exist, run the test with UPDATE_SNAPSHOTS=1 to create it"
+    );
+    let expected = image::open(&path).unwrap().into_rgba8();
+    if &expected != image {
+        let new_path = path.with_extension("new.png");
+        image.save(&new_path).unwrap();
+        panic!(
+            "snapshot {name} does not match, the new render was written to {}",
+            new_path.display()
+        );
+    }
+}
+
+#[test]
+fn test_rich_text_snapshot() {
+    const WIDTH: u32 = 640;
+    const HEIGHT: u32 = 48;
+    set_fonts(
+        FONTS
+            .iter()
+            .map(|font| fs::read(fixture_path("fixtures/fonts").join(font)).unwrap()),
+    );
+    let text = TextBuilder::new("plain ")
+        .size(24.0)
+        .font(Some("DejaVu Sans Mono"))
+        .append("red", SpanStyle::new().color(Color::from_rgb(220, 50, 50)))
+        .append(" ", SpanStyle::new())
+        .append("bold", SpanStyle::new().bold())
+        .append(" ", SpanStyle::new())
+        .append("italic", SpanStyle::new().italic())
+        .append(" ", SpanStyle::new())
+        .append(
+            "underline",
+            SpanStyle::new()
+                .underline()
+                .color(Color::from_rgb(60, 120, 220)),
+        )
+        .append(" ", SpanStyle::new())
+        .append("serif", SpanStyle::new().font("DejaVu Serif"))
+        .build();
+    let mut root = container(text)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .bg(Color::from_rgb(30, 30, 30))
+        .padding_all(8)
+        .into_dyn();
+    root.layout(UVec2::new(WIDTH, HEIGHT));
+
+    let mut draw_handle: DrawHandle = DrawHandle::from(OnwedBuffer::new(WIDTH, HEIGHT));
+    root.render(UVec2::ZERO, &mut draw_handle);
+    assert_snapshot(
+        "rich_text",
+        &to_image(draw_handle.get_bytes(), WIDTH, HEIGHT),
+    );
+}